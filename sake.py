@@ -1,12 +1,23 @@
 import argparse
+from concurrent.futures import ThreadPoolExecutor
+import csv
+import fnmatch
+import functools
 import json
 import os
-from datetime import datetime
+import random
+import re
+import shlex
+import shutil
+import textwrap
+import threading
+import time
+from datetime import datetime, timedelta, timezone
 from dateutil import parser
-from functools import partial
 from pathlib import Path
 import subprocess
 import sys
+import yaml
 
 from rich import box
 from rich.console import Console, RenderGroup
@@ -15,21 +26,235 @@ from rich.prompt import Confirm
 from rich.panel import Panel
 
 
+verbose = False
+na_string = ""
+local_dates = False
+absolute_paths = False
+emitted_warnings = []
+
+
+def debug(message):
+    if verbose:
+        print(message, file=sys.stderr)
+
+
+def warn(message):
+    emitted_warnings.append(message)
+    print(f"warning: {message}", file=sys.stderr)
+
+
+def format_cell(value):
+    return na_string if value is None else str(value)
+
+
+ANSI_ESCAPE = re.compile(r"\x1b\[[0-9;?]*[A-Za-z]|\x1b\][^\x07]*\x07")
+
+
+class AnsiStrippingWriter:
+    def __init__(self, stream):
+        self.stream = stream
+
+    def write(self, text):
+        return self.stream.write(ANSI_ESCAPE.sub("", text))
+
+    def __getattr__(self, name):
+        return getattr(self.stream, name)
+
+
+def parse_timestamp(value):
+    date = value.split(".")[0].rstrip("Z")
+    return datetime.fromisoformat(date)
+
+
+def format_duration(seconds):
+    seconds = int(seconds)
+    days, seconds = divmod(seconds, 86400)
+    hours, seconds = divmod(seconds, 3600)
+    minutes, seconds = divmod(seconds, 60)
+    if days > 0:
+        return f"{days}d{hours}h"
+    if hours > 0:
+        return f"{hours}h{minutes}m"
+    if minutes > 0:
+        return f"{minutes}m{seconds}s"
+    return f"{seconds}s"
+
+
+def display_time(date):
+    # timestamps are stored in UTC, only converted for humans with --local
+    if not local_dates:
+        return date
+    return date.replace(tzinfo=timezone.utc).astimezone().replace(tzinfo=None)
+
+
+def format_field(field, value):
+    if field == "duration" and value is not None:
+        return format_duration(value)
+    return format_cell(value)
+
+
+def format_relative_time(date):
+    seconds = int((datetime.utcnow() - date).total_seconds())
+    if seconds < 0:
+        return "in the future"
+    for unit, unit_seconds in [("year", 31536000), ("month", 2592000), ("week", 604800),
+                               ("day", 86400), ("hour", 3600), ("minute", 60), ("second", 1)]:
+        if seconds >= unit_seconds:
+            amount = seconds // unit_seconds
+            return f"{amount} {unit}{'s' if amount > 1 else ''} ago"
+    return "just now"
+
+
+def format_size(size):
+    for unit in ["B", "KiB", "MiB", "GiB", "TiB"]:
+        if size < 1024 or unit == "TiB":
+            break
+        size /= 1024
+    return f"{size} {unit}" if unit == "B" else f"{size:.1f} {unit}"
+
+
+MISSING = object()
+
+TREND_ARROWS = {"up": "↑", "down": "↓", "flat": "→"}
+
+
+def get_path(values, path, default_val=None):
+    if path in values:
+        return values[path]
+    for key in path.split("."):
+        if isinstance(values, list) and key.isdigit() and int(key) < len(values):
+            values = values[int(key)]
+        elif isinstance(values, dict) and key in values:
+            values = values[key]
+        else:
+            return default_val
+    return values
+
+
+def coerce(value, type_name):
+    try:
+        if type_name == "number":
+            return float(value)
+        if type_name == "bool":
+            if isinstance(value, str):
+                return {"true": True, "false": False}[value.strip().lower()]
+            return bool(value)
+        return str(value)
+    except (ValueError, KeyError, TypeError):
+        return None
+
+
+def parse_coercion(value):
+    field, _, type_name = value.rpartition(":")
+    if field == "" or type_name not in ("number", "bool", "string"):
+        raise argparse.ArgumentTypeError(f"expected FIELD:number, FIELD:bool or FIELD:string, got '{value}'")
+    return field, type_name
+
+
+def parse_parallelism(value):
+    if value == "auto":
+        return None
+    if not value.isdigit() or int(value) < 1:
+        raise argparse.ArgumentTypeError(f"expected a number of threads of at least 1 or auto, got '{value}'")
+    return int(value)
+
+
+def parse_alignment(value):
+    column, _, justify = value.rpartition(":")
+    if column == "" or justify not in ("left", "center", "right"):
+        raise argparse.ArgumentTypeError(f"expected COLUMN:left|center|right, got '{value}'")
+    return column, justify
+
+
+METRIC_AGGREGATIONS = {
+    "first": lambda values: values[0],
+    "last": lambda values: values[-1],
+    "mean": lambda values: sum(values) / len(values),
+    "max": max,
+    "min": min,
+}
+
+
 class Experiment(object):
+    # fields are looked up in the params, then the built-in fields, then the
+    # metrics. "metrics" looks up the metrics first, "params." and "metrics."
+    # prefixes always target one of them and "config." reads the experiment config.
+    precedence = "params"
+
+    FIELD_ALIASES = {
+        "n_checkpoints": "checkpoints",
+    }
+    BUILTIN_FIELDS = {
+        "state": "get_state",
+        "checkpoints": "get_n_checkpoints",
+        "script": "get_script",
+        "duration": "get_duration",
+        "primary": "get_primary",
+        "primary_name": "get_primary_name",
+        "step": "get_step",
+    }
+
     def __init__(self, expe_json):
         self.id = expe_json["id"]
-        date, _ = expe_json["created"].split(".")
-        self.created = datetime.fromisoformat(date)
+        self.created = parse_timestamp(expe_json["created"])
         self.params = expe_json["params"]
         self.checkpoints = expe_json["checkpoints"]
         self.command = expe_json["command"]
+        self.json = expe_json
+        self.at_step = None
+        self.metric_agg = None
+        self.coercions = {}
+        self.location = None
+        self.file_path = None
+        self.mtime = None
+        self.field_cache = {}
+        self.stale_after = timedelta(days=1)
 
     def get_field(self, field, default_val=None):
+        # filters, sorting and output resolve the same fields many times
+        key = (field, self.at_step, self.metric_agg, self.precedence, self.stale_after)
+        if key not in self.field_cache:
+            self.field_cache[key] = self._resolve_field(field, MISSING)
+        value = self.field_cache[key]
+        if value is MISSING:
+            value = default_val
+        if field in self.coercions and value is not None:
+            value = coerce(value, self.coercions[field])
+        return value
+
+    def _resolve_field(self, field, default_val=None):
+        field = self.FIELD_ALIASES.get(field, field)
+        if field.startswith("params."):
+            return get_path(self.params, field[len("params."):], default_val)
+        if field.startswith("metrics."):
+            return self.get_metric(field[len("metrics."):], default_val)
+        if field.startswith("config."):
+            return get_path(self.json.get("config") or {}, field[len("config."):], default_val)
+
+        if self.precedence == "metrics":
+            value = self.get_metric(field, MISSING)
+            if value is not MISSING:
+                return value
+
         if field in self.params:
             return self.params[field]
 
+        if field in self.BUILTIN_FIELDS:
+            return getattr(self, self.BUILTIN_FIELDS[field])()
+
+        return self.get_metric(field, default_val)
+
+    def get_metric(self, field, default_val=None):
         if self.checkpoints is None:
             return default_val
+        if self.at_step is not None:
+            checkpoint = self.get_checkpoint_at_step(self.at_step)
+            if checkpoint is None:
+                return default_val
+            return checkpoint["metrics"].get(field, default_val)
+        if self.metric_agg is not None:
+            value = self.aggregate_metric(field)
+            return default_val if value is None else value
         _, best_checkpoint = self.get_best_checkpoint()
         if field in best_checkpoint["metrics"]:
             return best_checkpoint["metrics"][field]
@@ -40,18 +265,48 @@ class Experiment(object):
 
         return default_val
 
+    def get_metric_history(self, name):
+        history = [
+            (checkpoint["step"], checkpoint["metrics"].get(name))
+            for checkpoint in self.checkpoints or []
+        ]
+        return sorted(
+            (step, value) for step, value in history
+            if isinstance(value, (int, float)) and not isinstance(value, bool)
+        )
+
+    def aggregate_metric(self, name):
+        values = [value for _, value in self.get_metric_history(name)]
+        if len(values) == 0:
+            return None
+        aggregation = self.metric_agg
+        if aggregation == "best":
+            # only primary metrics have a goal, higher is better for the others
+            aggregation = "min" if self.get_goal(name) == "minimize" else "max"
+        return METRIC_AGGREGATIONS[aggregation](values)
+
+    def get_value(self, field, default_val=None):
+        if field in ("id", "created", "command"):
+            return getattr(self, field)
+        return self.get_field(field, default_val)
+
     @staticmethod
     def _present(values, num_values=5):
         if num_values is None:
             num_values = 5
 
-        def maybe_trim(value: str):
+        def maybe_trim(value):
             # TODO: trim after ":"
+            style, suffix = None, ""
+            if isinstance(value, tuple):
+                value, style, suffix = (value + ("",))[:3]
             MAX_LENGTH = 60
-            if len(value) > MAX_LENGTH:
+            if len(value) > MAX_LENGTH and "\n" not in value:
                 split_idx = max(MAX_LENGTH-3, value.index(":"))
                 value = value[:split_idx] + "..."
-            return value
+            if style is not None:
+                value = f"[{style}]{value}[/{style}]"
+            return value + suffix
 
         if len(values) >= num_values:
             values = values[:num_values+1] + ["..."]
@@ -75,11 +330,48 @@ class Experiment(object):
             return f"{value:.3f}"
         return value
 
-    def get_params(self, select=[], show_all=False):
+    @staticmethod
+    def _present_param(key, value, pretty):
+        if pretty and isinstance(value, (dict, list)) and len(value) > 0:
+            return f"{key}:\n" + textwrap.indent(json.dumps(value, indent=2), "  ")
+        return f"{key}: {Experiment._present_value(value)}"
+
+    def get_params(self, select=[], show_all=False, pretty=False):
         items, _ = self._select(self.params, select)
-        values = [f"{key}: {self._present_value(value)}" for key, value in items]
+        values = [self._present_param(key, value, pretty) for key, value in items]
         return self._present(values, num_values=10000 if show_all else None)
 
+    def to_row(self, select=None, null_as_missing=False):
+        # with a selection, only the selected fields the experiment has, see fill_selected
+        row = {
+            "id": self.id,
+            "created": self.created.isoformat(),
+            "command": self.command,
+            "config": self.json.get("config"),
+            "params": dict(self.params) if select is None else {
+                name: self.params[name] for name in select if name in self.params
+            },
+            "step": None,
+            "metrics": {},
+        }
+        if null_as_missing and "config" not in self.json:
+            del row["config"]
+        for name in select or []:
+            if name in self.BUILTIN_FIELDS and name not in self.params:
+                row[name] = self.get_field(name)
+        checkpoint = self.get_displayed_checkpoint()
+        if checkpoint is not None:
+            metrics = checkpoint["metrics"]
+            row["step"] = checkpoint["step"]
+            row["metrics"] = dict(metrics) if select is None else {
+                name: metrics[name] for name in select if name in metrics
+            }
+            if self.at_step is None and self.metric_agg is not None:
+                row["metrics"] = {key: self.aggregate_metric(key) for key in row["metrics"]}
+        elif null_as_missing:
+            del row["step"]
+        return row
+
     def get_keys(self):
         metrics = self.get_metrics()
         return metrics.keys()
@@ -92,15 +384,25 @@ class Experiment(object):
         metrics = sorted(items, key=lambda x: -int(x[0] == name))
         return metrics
 
-    def get_metrics(self, select=[], show_all=False):
+    def get_metrics(self, select=[], show_all=False, styles={}, trends=False):
         if self.checkpoints is None:
             return "0 checkpoints"
         name, checkpoint = self.get_best_checkpoint()
+        label = "(best)"
+        if self.at_step is not None:
+            checkpoint, label = self.get_checkpoint_at_step(self.at_step), ""
+            if checkpoint is None:
+                return format_cell(None)
         items, selected = self._select(checkpoint["metrics"], select)
         metrics = sorted(items, key=lambda x: -int(x[0] == name))
-        step = checkpoint["step"]
-        values = [f"step {step} (best)"] + [
-            f"{key}: {self._present_value(value)}"
+        header = f"step {checkpoint['step']} {label}".strip()
+        if self.at_step is None and self.metric_agg is not None:
+            header = f"{self.metric_agg} of {len(self.checkpoints)} checkpoints"
+            metrics = [(key, format_cell(self.aggregate_metric(key))) for key, _ in metrics]
+            trends = False
+        values = [header] + [
+            (f"{key}: {self._present_value(value)}", styles.get(key),
+             self.get_trend_arrow(checkpoint, key) if trends else "")
             for key, value in metrics
         ]
         num_values = None
@@ -110,6 +412,37 @@ class Experiment(object):
             num_values = len(items)
         return self._present(values, num_values=num_values)
 
+    def get_previous_checkpoint(self, checkpoint):
+        previous = [other for other in self.checkpoints if other["step"] < checkpoint["step"]]
+        if len(previous) == 0:
+            return None
+        return max(previous, key=lambda other: other["step"])
+
+    def get_trend(self, checkpoint, metric):
+        previous = self.get_previous_checkpoint(checkpoint)
+        if previous is None:
+            return None
+        before, after = previous["metrics"].get(metric), checkpoint["metrics"].get(metric)
+        if not isinstance(before, (int, float)) or not isinstance(after, (int, float)):
+            return None
+        if after > before:
+            return "up"
+        if after < before:
+            return "down"
+        return "flat"
+
+    def get_trend_arrow(self, checkpoint, metric):
+        trend = self.get_trend(checkpoint, metric)
+        if trend is None:
+            return ""
+        arrow = TREND_ARROWS[trend]
+        goal = self.get_goal(metric)
+        if trend == "flat" or goal is None:
+            return f" {arrow}"
+        improved = (trend == "up") == (goal == "maximize")
+        style = "green" if improved else "red"
+        return f" [{style}]{arrow}[/{style}]"
+
     def get_best_checkpoint(self):
         metrics = {}
         for checkpoint in self.checkpoints:
@@ -131,7 +464,104 @@ class Experiment(object):
                 checkpoint_idx = i + 1
         return name, self.checkpoints[checkpoint_idx]
 
+    def get_checkpoint_at_step(self, step):
+        if self.checkpoints is None:
+            return None
+        for checkpoint in self.checkpoints:
+            if checkpoint["step"] == step:
+                return checkpoint
+        return None
+
+    def get_displayed_checkpoint(self):
+        if self.at_step is not None:
+            return self.get_checkpoint_at_step(self.at_step)
+        if self.checkpoints is None:
+            return None
+        _, checkpoint = self.get_best_checkpoint()
+        return checkpoint
+
+    def get_goal(self, metric):
+        if self.checkpoints is None:
+            return None
+        for checkpoint in self.checkpoints:
+            if checkpoint["primary_metric"]["name"] == metric:
+                return checkpoint["primary_metric"]["goal"]
+        return None
+
+    def has_checkpoint_on_disk(self):
+        if self.checkpoints is None or self.location is None:
+            return False
+        return any(
+            (self.location / checkpoint["path"]).exists()
+            for checkpoint in self.checkpoints
+            if checkpoint.get("path") is not None
+        )
+
+    def has_path_on_disk(self):
+        path = self.json.get("path")
+        if path is None or self.location is None:
+            return False
+        return (self.location / path).exists()
+
+    def display_path(self, path):
+        if not absolute_paths or path is None or self.location is None:
+            return path
+        return str((self.location / path).resolve())
+
+    def get_primary_name(self):
+        if self.checkpoints is None or len(self.checkpoints) == 0:
+            return None
+        name, _ = self.get_best_checkpoint()
+        return name
+
+    def get_primary(self):
+        if self.checkpoints is None or len(self.checkpoints) == 0:
+            return None
+        name, checkpoint = self.get_best_checkpoint()
+        return checkpoint["metrics"].get(name)
+
+    def get_step(self):
+        # the step of the best checkpoint, or of the one read with --at-step
+        checkpoint = self.get_displayed_checkpoint()
+        return None if checkpoint is None else checkpoint["step"]
+
+    def get_n_checkpoints(self):
+        return len(self.checkpoints) if self.checkpoints is not None else 0
+
+    def get_script(self):
+        try:
+            tokens = shlex.split(self.command)
+        except ValueError:
+            tokens = self.command.split()
+        for token in tokens:
+            if token.endswith(".py"):
+                return token
+        # keepsake stores the command without the interpreter
+        arguments = [token for token in tokens[1:] if not token.startswith("-")]
+        if len(arguments) > 0 and os.path.basename(tokens[0]).startswith("python"):
+            return arguments[0]
+        return self.command
+
+    def get_duration(self):
+        # seconds between the creation of the experiment and its latest checkpoint
+        if self.checkpoints is None or len(self.checkpoints) == 0:
+            return None
+        last_created = max(parse_timestamp(checkpoint["created"]) for checkpoint in self.checkpoints)
+        return (last_created - self.created).total_seconds()
+
+    def get_state(self):
+        # keepsake does not record whether a run is still going, so this is
+        # inferred from the age of the latest checkpoint
+        if self.checkpoints is None or len(self.checkpoints) == 0:
+            return "empty"
+        last_created = max(parse_timestamp(checkpoint["created"]) for checkpoint in self.checkpoints)
+        if datetime.utcnow() - last_created > self.stale_after:
+            return "stale"
+        return "active"
+
     def get_best_step(self):
+        if self.checkpoints is None:
+            return None
         _, checkpoint = self.get_best_checkpoint()
         return None if checkpoint is None else checkpoint["step"] 
 
@@ -141,47 +571,315 @@ class Experiment(object):
             expe_json = json.load(f)
         return Experiment(expe_json)
 
+    def to_json(self, indent=None):
+        # the json as stored, which keeps the fields sake does not model
+        return json.dumps(self.json, indent=indent)
+
+
+def is_glob(pattern):
+    return any(char in pattern for char in "*?[")
+
+
+class InvalidRepository(Exception):
+    pass
+
+
+class ExperimentNotFound(Exception):
+    pass
+
+
+class AmbiguousExperiment(Exception):
+    pass
+
+
+class MissingCheckpoint(Exception):
+    pass
+
+
+class InvalidArguments(Exception):
+    pass
+
 
 class KeepsakeRepository(object):
-    def __init__(self):
-        self.location = self._get_location()
+    CONFIG_KEYS = ("repository", "storage", "include")
+
+    def __init__(self, location=None):
+        # without a location, the repository is the one configured in keepsake.yml
+        if location is None:
+            self.config_path = self._find_config(Path.cwd())
+            self.config = self._read_config(self.config_path)
+        else:
+            self.config_path = Path("keepsake.yml").absolute()
+            self.config = {"repository": location}
+        self.storage = self.config.get("storage")
+        self.location = self._get_location(self.config_path, self.config)
+        debug(f"repository location: {self.location}")
+
+    @property
+    def state_dir(self):
+        # sake's own state, next to keepsake.yml
+        return self.config_path.parent / ".sake"
+
+    def get_tombstones(self):
+        tombstones_path = self.state_dir / "tombstones.jsonl"
+        if not tombstones_path.exists():
+            return []
+        with open(tombstones_path) as f:
+            return [json.loads(line) for line in f if line.strip() != ""]
+
+    def add_tombstone(self, expe, reason=None):
+        self.state_dir.mkdir(exist_ok=True)
+        tombstone = {"id": expe.id, "deleted": datetime.utcnow().isoformat(), "reason": reason}
+        with open(self.state_dir / "tombstones.jsonl", "a") as f:
+            f.write(json.dumps(tombstone) + "\n")
+
+    def get_tags(self):
+        tags_path = self.state_dir / "tags.json"
+        if not tags_path.exists():
+            return {}
+        with open(tags_path) as f:
+            return json.load(f)
+
+    def set_tag(self, expe, tag):
+        tags = self.get_tags()
+        for expe_id, other_tag in tags.items():
+            if other_tag == tag and expe_id != expe.id:
+                raise InvalidArguments(f"tag '{tag}' is already used by experiment {expe_id[:7]}")
+        if expe.id in tags and tags[expe.id] != tag:
+            warn(f"experiment {expe.id[:7]} was tagged '{tags[expe.id]}', replacing it with '{tag}'")
+        tags[expe.id] = tag
+        self._write_tags(tags)
+
+    def remove_tag(self, expe):
+        tags = self.get_tags()
+        if expe.id in tags:
+            del tags[expe.id]
+            self._write_tags(tags)
+
+    def _write_tags(self, tags):
+        self.state_dir.mkdir(exist_ok=True)
+        with open(self.state_dir / "tags.json", "w") as f:
+            json.dump(tags, f, indent=2)
 
     def _get_experiments_files(self):
         metadata_dir = self.location / "metadata/experiments"
-        experiment_files = os.listdir(metadata_dir)
+        experiment_files = sorted(os.listdir(metadata_dir))
         return [
             os.path.join(metadata_dir, experiment_file)
             for experiment_file in experiment_files
         ]
 
-    def get_experiments(self):
+    def get_experiments(self, parallelism=None, duplicates="first", timeout=None):
         experiment_files = self._get_experiments_files()
-        experiments = [
-            Experiment.from_file(file_path)
-            for file_path in experiment_files
-        ]
+        deadline = None if timeout is None else time.monotonic() + timeout.total_seconds()
+
+        # daemon workers, so that a read blocked past the deadline cannot keep the process alive
+        experiments = [None] * len(experiment_files)
+        errors = []
+        pending = iter(enumerate(experiment_files))
+        lock = threading.Lock()
+
+        def load():
+            while len(errors) == 0:
+                with lock:
+                    index, file_path = next(pending, (None, None))
+                if file_path is None:
+                    return
+                try:
+                    experiments[index] = self._try_load_experiment(file_path)
+                except Exception as e:
+                    errors.append(e)
+
+        # same default number of workers as ThreadPoolExecutor
+        n_workers = parallelism or min(32, (os.cpu_count() or 1) + 4)
+        workers = [threading.Thread(target=load, daemon=True) for _ in range(min(n_workers, len(experiment_files)))]
+        for worker in workers:
+            worker.start()
+        for worker in workers:
+            worker.join(None if deadline is None else max(0, deadline - time.monotonic()))
+            if worker.is_alive():
+                raise TimeoutError(f"loading experiments took longer than {timeout}")
+        if len(errors) > 0:
+            raise errors[0]
+
+        experiments = [expe for expe in experiments if expe is not None]
+        return self._dedupe(experiments, duplicates)
+
+    @staticmethod
+    def _dedupe(experiments, duplicates):
+        by_id = {}
+        for expe in experiments:
+            by_id.setdefault(expe.id, []).append(expe)
+
+        experiments = []
+        for expe_id, candidates in by_id.items():
+            if len(candidates) > 1:
+                files = ", ".join(expe.file_path for expe in candidates)
+                warn(f"experiment {expe_id[:7]} is defined in several files: {files}")
+                if duplicates == "latest":
+                    candidates = sorted(candidates, key=lambda expe: -os.path.getmtime(expe.file_path))
+            experiments.append(candidates[0])
         return experiments
 
+    def _try_load_experiment(self, file_path):
+        try:
+            return self._load_experiment(file_path)
+        except (ValueError, KeyError, TypeError) as e:
+            warn(f"skipping malformed experiment file {file_path} ({type(e).__name__}: {e})")
+            return None
+
+    def get_checkpoints(self, experiments=None):
+        if experiments is None:
+            experiments = self.get_experiments()
+        for expe in experiments:
+            for checkpoint in expe.checkpoints or []:
+                yield expe, checkpoint
+
+    def _load_experiment(self, file_path):
+        expe = Experiment.from_file(file_path)
+        expe.location = self.location
+        expe.file_path = file_path
+        expe.mtime = os.path.getmtime(file_path)
+        return expe
+
+    def find_experiments(self, pattern):
+        experiment_files = self._get_experiments_files()
+        experiment_files = [
+            f for f in experiment_files
+            if fnmatch.fnmatchcase(os.path.splitext(os.path.basename(f))[0], pattern)
+        ]
+        if len(experiment_files) == 0:
+            raise ExperimentNotFound(pattern)
+        return [self._load_experiment(file_path) for file_path in experiment_files]
+
     def get_experiment(self, expe_partial_id):
+        # tags are checked before ids, so a tag like "abc" hides ids starting with it
+        tagged = {tag: expe_id for expe_id, tag in self.get_tags().items()}
+        expe_partial_id = tagged.get(expe_partial_id, expe_partial_id)
+        if is_glob(expe_partial_id):
+            experiments = self.find_experiments(expe_partial_id)
+            if len(experiments) >= 2:
+                raise AmbiguousExperiment(f"Found {len(experiments)} experiments matching '{expe_partial_id}'")
+            return experiments[0]
+
         experiment_files = self._get_experiments_files()
         experiment_files= list(filter(lambda f: os.path.basename(f).startswith(expe_partial_id), experiment_files))
         n_expe = len(experiment_files)
         if n_expe >= 2:
-            raise Exception(f"Found {n_expe} experiments with id '{expe_partial_id}'")
+            raise AmbiguousExperiment(f"Found {n_expe} experiments with id '{expe_partial_id}'")
         if n_expe == 0:
-            raise KeyError(expe_partial_id)
-        return Experiment.from_file(experiment_files[0])
+            raise ExperimentNotFound(expe_partial_id)
+        return self._load_experiment(experiment_files[0])
+
+    def get_stored_files(self, expe):
+        stored_files = [Path(expe.file_path), self.location / "experiments" / f"{expe.id}.tar.gz"]
+        stored_files += [
+            self.location / "checkpoints" / f"{checkpoint['id']}.tar.gz"
+            for checkpoint in expe.checkpoints or []
+        ]
+        return [path for path in stored_files if path.exists()]
 
     @staticmethod
-    def _get_location():
-        with open("keepsake.yml") as f:
-            lines = f.readlines()
-        for line in lines:
-            if line.startswith("repository:"):
-                _, location, _ = line.split('"')
-                assert location.startswith("file://")
-                return Path(location[7:])
-        raise Exception("repository not found in keepsake.yml")
+    @functools.lru_cache(maxsize=None)
+    def _find_config(cwd):
+        # searched once per directory, the settings and the repository share the result
+        for directory in [cwd, *cwd.parents]:
+            config_paths = [
+                directory / name
+                for name in ("keepsake.yml", "keepsake.yaml")
+                if (directory / name).exists()
+            ]
+            if len(config_paths) > 1:
+                warn(f"both keepsake.yml and keepsake.yaml exist in {directory}, using keepsake.yml")
+            if len(config_paths) > 0:
+                return config_paths[0]
+        raise InvalidRepository("keepsake.yml not found in the current directory or its parents")
+
+    @staticmethod
+    def _read_config(config_path, including=None):
+        with open(config_path) as f:
+            try:
+                config = yaml.safe_load(f)
+            except yaml.YAMLError as e:
+                raise InvalidRepository(f"invalid yaml in {config_path.name} ({e})")
+        if config is None:
+            config = {}
+        if not isinstance(config, dict):
+            raise InvalidRepository(f"expected a mapping in {config_path.name}, got {type(config).__name__}")
+
+        for key in config:
+            if verbose and key not in KeepsakeRepository.CONFIG_KEYS:
+                warn(f"unknown key '{key}' in {config_path.name}")
+
+        if "include" in config:
+            include_path = (config_path.parent / config.pop("include")).resolve()
+            if include_path in (config_path.resolve(), including):
+                raise InvalidRepository(f"cyclic include of {include_path} in {config_path}")
+            if including is not None:
+                raise InvalidRepository(f"{config_path} is included and cannot include another file")
+            if not include_path.exists():
+                raise InvalidRepository(f"included file {include_path} not found")
+            # keys of the including file win over the base config
+            config = {**KeepsakeRepository._read_config(include_path, config_path.resolve()), **config}
+        return config
+
+    @staticmethod
+    def _get_location(config_path, config):
+        if "repository" not in config:
+            raise InvalidRepository(f"repository not found in {config_path.name}")
+        if not isinstance(config["repository"], str):
+            raise InvalidRepository(f"repository in {config_path.name} should be a string")
+        # relative locations are relative to keepsake.yml, not to the cwd
+        return config_path.parent / KeepsakeRepository._parse_location(config["repository"])
+
+    @staticmethod
+    def _parse_location(location):
+        if location.startswith("file://"):
+            return Path(location[7:])
+        if "://" in location:
+            scheme, _ = location.split("://", 1)
+            raise InvalidRepository(f"unsupported repository scheme '{scheme}://'")
+        warn(f"repository '{location}' has no scheme, assuming a local path (use \"file://{location}\")")
+        return Path(location)
+
+
+def load_sake_config():
+    config_path = KeepsakeRepository._find_config(Path.cwd()).parent / ".sake.toml"
+    if not config_path.exists():
+        return {}
+    try:
+        import tomllib  # python >= 3.11
+    except ImportError:
+        try:
+            import tomli as tomllib
+        except ImportError:
+            raise ImportError(f"reading {config_path.name} requires python >= 3.11 or the tomli package")
+
+    with open(config_path, "rb") as f:
+        return tomllib.load(f)
+
+
+def sake_setting(key, default=None):
+    try:
+        return load_sake_config().get(key, default)
+    except InvalidRepository:
+        return default
+    except ImportError as e:
+        # settings have a default, unlike views that cannot be listed without .sake.toml
+        warn(f"{e}, ignoring its settings")
+        return default
+
+
+def load_view(view):
+    if os.path.isfile(view):
+        with open(view) as f:
+            lines = [line.strip() for line in f]
+        return [line for line in lines if line != "" and not line.startswith("#")]
+
+    views = load_sake_config().get("views", {})
+    if view not in views:
+        raise InvalidArguments(f"view '{view}' is neither a file nor defined in .sake.toml")
+    return list(views[view])
 
 
 def try_fallback(func, default_val):
@@ -191,24 +889,176 @@ def try_fallback(func, default_val):
         return default_val
 
 
+DURATION_UNITS = {
+    "s": 1, "sec": 1, "second": 1, "seconds": 1,
+    "m": 60, "min": 60, "minute": 60, "minutes": 60,
+    "h": 3600, "hour": 3600, "hours": 3600,
+    "d": 86400, "day": 86400, "days": 86400,
+    "w": 604800, "week": 604800, "weeks": 604800,
+}
+
+
+def parse_duration(value):
+    parts = re.findall(r"(\d+(?:\.\d+)?)\s*([a-z]+)", value.lower())
+    if len(parts) == 0 or re.sub(r"[\d.a-z\s]", "", value.lower()) != "":
+        raise ValueError(f"invalid duration '{value}'")
+    seconds = 0
+    for amount, unit in parts:
+        if unit not in DURATION_UNITS:
+            raise ValueError(f"invalid duration unit '{unit}' in '{value}'")
+        seconds += float(amount) * DURATION_UNITS[unit]
+    return timedelta(seconds=seconds)
+
+
+class DateWindow:
+    # compares against a whole period, so that created=today matches any time of the day
+    def __init__(self, start, end):
+        self.start = start
+        self.end = end
+
+    def __eq__(self, other):
+        return self.start <= other < self.end
+
+    def __ne__(self, other):
+        return not self == other
+
+    # reflected comparisons, "created < window" calls window > created
+    def __gt__(self, other):
+        return other < self.start
+
+    def __ge__(self, other):
+        return other < self.end
+
+    def __lt__(self, other):
+        return other >= self.end
+
+    def __le__(self, other):
+        return other >= self.start
+
+
+def parse_date_token(value):
+    # day boundaries are taken in UTC like stored timestamps, or in local time with --local
+    now = datetime.now() if local_dates else datetime.utcnow()
+    midnight = now.replace(hour=0, minute=0, second=0, microsecond=0)
+    if value == "today":
+        start, days = midnight, 1
+    elif value == "yesterday":
+        start, days = midnight - timedelta(days=1), 1
+    elif value == "this-week":
+        start, days = midnight - timedelta(days=midnight.weekday()), 7
+    else:
+        return None
+    end = start + timedelta(days=days)
+    if local_dates:
+        start, end = (date.astimezone(timezone.utc).replace(tzinfo=None) for date in (start, end))
+    return DateWindow(start, end)
+
+
+def parse_date(value):
+    window = parse_date_token(value)
+    if window is not None:
+        return window
+    # timestamps are stored in UTC, "now-1h" is relative to the current UTC time
+    if value.startswith("now"):
+        offset = value[3:].strip()
+        if offset == "":
+            return datetime.utcnow()
+        if offset[0] not in "+-":
+            raise ValueError(f"invalid date '{value}'")
+        sign = -1 if offset[0] == "-" else 1
+        return datetime.utcnow() + sign * parse_duration(offset[1:])
+    date = parser.parse(value, parserinfo=parser.parserinfo(dayfirst=True))
+    if date.tzinfo is None and not local_dates:
+        return date
+    # astimezone reads naive dates as local time
+    return date.astimezone(timezone.utc).replace(tzinfo=None)
+
+
+def parse_cutoff(value):
+    # file modification times are compared in local time
+    try:
+        return datetime.now() - parse_duration(value)
+    except ValueError:
+        date = parser.parse(value, parserinfo=parser.parserinfo(dayfirst=True))
+    if date.tzinfo is not None:
+        # 2026-01-01T00:00:00Z, to local time like the modification times
+        date = date.astimezone().replace(tzinfo=None)
+    return date
+
+
+def field_value(expe, field):
+    # count(checkpoints) counts the checkpoints, not the n_checkpoints built-in
+    return expe.checkpoints if field == "checkpoints" else Filter(None, field, None).resolve(expe)[0]
+
+
+def field_len(expe, field):
+    value = field_value(expe, field)
+    return len(value) if isinstance(value, (str, list, dict)) else None
+
+
+def field_count(expe, field):
+    value = field_value(expe, field)
+    if value is None:
+        return 0
+    return len(value) if isinstance(value, (list, dict)) else 1
+
+
+def field_improvement(expe, field):
+    # change from the first to the last checkpoint, positive when the metric got better.
+    # Only primary metrics have a goal, for the others "better" is unknown.
+    history = expe.get_metric_history(field)
+    goal = expe.get_goal(field)
+    if len(history) < 2 or goal is None:
+        return None
+    change = history[-1][1] - history[0][1]
+    return -change if goal == "minimize" else change
+
+
+FIELD_FUNCTIONS = {"len": field_len, "count": field_count, "improvement": field_improvement}
+FIELD_FUNCTION_PATTERN = re.compile(r"(\w+)\((.+)\)")
+
+
 class Filter:
-    def __init__(self, comp, field, value):
+    def __init__(self, comp, field, value, stringify=False, ignore_case=False, convert=True, require_numeric=False):
         self.comp = comp
         self.field = field
         self.value = value
-
-    def __call__(self, expe):
+        self.stringify = stringify
+        self.ignore_case = ignore_case
+        self.convert = convert
+        self.require_numeric = require_numeric
+
+    def resolve(self, expe):
+        match = FIELD_FUNCTION_PATTERN.fullmatch(self.field)
+        if match is not None:
+            function, field = match.group(1), match.group(2).strip()
+            return FIELD_FUNCTIONS[function](expe, field), float
         if self.field == "created":
             field = expe.created
-            convert_func = partial(parser.parse, parserinfo=parser.parserinfo(dayfirst=True))
-        elif self.field == "n_checkpoints":
-            field = len(expe.checkpoints) if expe.checkpoints is not None else 0
-            convert_func = int
+            convert_func = parse_date
+        elif self.field in ("id", "command"):
+            field = getattr(expe, self.field)
+            convert_func = str
         else:
             field = expe.get_field(self.field)
             convert_func = type(field)
+        return field, convert_func
+
+    def __call__(self, expe):
+        field, convert_func = self.resolve(expe)
+
+        if self.require_numeric and (isinstance(field, bool) or not isinstance(field, (int, float))):
+            warn(f"experiment {expe.id[:7]} has no numeric value for '{self.field}' (got {field!r})")
+            return False
+
+        if self.stringify:
+            if field is None:
+                return False
+            field, convert_func = str(field), str
 
-        comp_value = try_fallback(convert_func, self.value)
+        comp_value = try_fallback(convert_func, self.value) if self.convert else self.value
+        if self.ignore_case and isinstance(field, str) and isinstance(comp_value, str):
+            field, comp_value = field.lower(), comp_value.lower()
         try:
             res = self.comp(field, comp_value) 
         except:
@@ -217,81 +1067,658 @@ class Filter:
         return res
 
 
-def compile_filter(format):
-    if " or " in format:
-        lhs_format, rhs_format = format.split(" or ")
-        lhs, rhs = compile_filter(lhs_format), compile_filter(rhs_format)
-        return lambda expe: lhs(expe) or rhs(expe)
+class CommandFilter:
+    def __init__(self, command):
+        self.command = command
+
+    def __call__(self, expe):
+        process = subprocess.run(
+            self.command,
+            shell=True,
+            input=expe.to_json().encode(),
+            stdout=subprocess.DEVNULL,
+        )
+        # exit code 1 excludes, anything else is a failure of the predicate
+        if process.returncode not in (0, 1):
+            warn(f"'{self.command}' failed on experiment {expe.id[:7]} (exit code {process.returncode})")
+        return process.returncode == 0
+
+    def filter(self, experiments):
+        # One process is spawned per experiment, run them concurrently.
+        executor = ThreadPoolExecutor()
+        try:
+            keep = list(executor.map(self, experiments))
+        finally:
+            # an interrupt must not wait for the commands still running
+            executor.shutdown(wait=False, cancel_futures=True)
+        return [expe for expe, kept in zip(experiments, keep) if kept]
+
+
+def array_contains(array, value):
+    if not isinstance(array, list):
+        return False
+    # other elements are compared to the value read as json, so "false" does not match true
+    try:
+        parsed = json.loads(value)
+    except ValueError:
+        parsed = value
+    return any(
+        element == value if isinstance(element, str)
+        else element == parsed and isinstance(element, bool) == isinstance(parsed, bool)
+        for element in array
+    )
+
+
+def in_range(value, bounds):
+    low, high, low_open, high_open = bounds
+    if isinstance(value, datetime):
+        convert_func = parse_date
+    else:
+        convert_func = float if isinstance(value, (int, float)) else type(value)
+    low, high = try_fallback(convert_func, low), try_fallback(convert_func, high)
+    above = value > low if low_open else value >= low
+    below = value < high if high_open else value <= high
+    return above and below
+
+
+# "<" next to ".." excludes that bound: 10..20, 10<..20, 10..<20, 10<..<20
+RANGE_PATTERN = re.compile(r"(.+?)(<?)\.\.(<?)(.+)")
+
+
+class InvalidFilter(Exception):
+    pass
+
+
+def is_number(value):
+    try:
+        float(value)
+    except ValueError:
+        return False
+    return True
+
+
+# checked in order, so that "<=" is matched before "<" and "="
+FILTER_OPERATORS = [
+    ("^=", lambda a, b: a.startswith(b)),
+    ("$=", lambda a, b: a.endswith(b)),
+    ("!=", lambda a, b: a != b),
+    ("<=", lambda a, b: a <= b),
+    (">=", lambda a, b: a >= b),
+    ("<", lambda a, b: a < b),
+    ("=", lambda a, b: a == b),
+    (">", lambda a, b: a > b),
+]
+
+
+class FilterParser:
+    # or < and < not < parentheses, keywords are case-insensitive:
+    #   (optimizer=adam or optimizer=sgd) and not lr>0.1
+    KEYWORD = re.compile(r"\s+(and|or)\s+", re.IGNORECASE)
+    NOT = re.compile(r"not\s+", re.IGNORECASE)
+
+    def __init__(self, format, ignore_case=False, require_numeric=False):
+        self.format = format
+        self.masked = mask_quotes(format)
+        self.position = 0
+        self.ignore_case = ignore_case
+        self.require_numeric = require_numeric
+
+    def parse(self):
+        filter = self.parse_or()
+        self.skip_spaces()
+        if self.position < len(self.format):
+            raise InvalidFilter(f"unexpected ')' at offset {self.position} in filter '{self.format}'")
+        return filter
+
+    def skip_spaces(self):
+        while self.position < len(self.format) and self.format[self.position].isspace():
+            self.position += 1
+
+    def keyword(self, name):
+        match = self.KEYWORD.match(self.masked, self.position)
+        if match is None or match.group(1).lower() != name:
+            return False
+        self.position = match.end()
+        return True
+
+    def parse_or(self):
+        operands = [self.parse_and()]
+        while self.keyword("or"):
+            operands.append(self.parse_and())
+        if len(operands) == 1:
+            return operands[0]
+        return lambda expe: any(operand(expe) for operand in operands)
+
+    def parse_and(self):
+        operands = [self.parse_not()]
+        while self.keyword("and"):
+            operands.append(self.parse_not())
+        if len(operands) == 1:
+            return operands[0]
+        return lambda expe: all(operand(expe) for operand in operands)
+
+    def parse_not(self):
+        self.skip_spaces()
+        match = self.NOT.match(self.format, self.position)
+        if match is None:
+            return self.parse_group()
+        self.position = match.end()
+        operand = self.parse_not()
+        return lambda expe: not operand(expe)
+
+    def parse_group(self):
+        start = self.position
+        if self.format[start:start + 1] != "(":
+            return self.parse_condition()
+        self.position += 1
+        filter = self.parse_or()
+        self.skip_spaces()
+        if self.format[self.position:self.position + 1] != ")":
+            raise InvalidFilter(f"unclosed '(' at offset {start} in filter '{self.format}'")
+        self.position += 1
+        return filter
+
+    def parse_condition(self):
+        # parentheses that are part of a condition, like len(command), are kept in it
+        start, depth = self.position, 0
+        while self.position < len(self.format):
+            char = self.masked[self.position]
+            if depth == 0 and (char == ")" or self.KEYWORD.match(self.masked, self.position)):
+                break
+            depth += {"(": 1, ")": -1}.get(char, 0)
+            self.position += 1
+        condition = self.format[start:self.position]
+        if condition.strip() == "":
+            raise InvalidFilter(f"expected a condition at offset {start} in filter '{self.format}'")
+        return compile_condition(condition, self.ignore_case, self.require_numeric)
+
+
+def compile_filter(format, ignore_case=False, require_numeric=False):
+    return FilterParser(format, ignore_case, require_numeric).parse()
+
+
+def mask_quotes(format):
+    # hides quoted values so that operators and keywords inside them are ignored
+    return re.sub(r"'[^']*'|\"[^\"]*\"", lambda match: "_" * len(match.group(0)), format)
+
+
+def unquote(value):
+    if len(value) >= 2 and value[0] == value[-1] and value[0] in "'\"":
+        return value[1:-1]
+    return value
+
+
+def compile_condition(format, ignore_case=False, require_numeric=False):
+    if format.strip() == "checkpoint-exists":
+        return lambda expe: expe.has_checkpoint_on_disk()
+
+    if format.strip() == "path-exists":
+        return lambda expe: expe.has_path_on_disk()
+
+    masked = mask_quotes(format)
+    split = lambda index, length: (format[:index].strip(), format[index + length:].strip())
+
+    if " between " in masked:
+        field, value = split(masked.index(" between "), len(" between "))
+        match = RANGE_PATTERN.fullmatch(value)
+        if match is None:
+            raise InvalidFilter(f"expected a range like 10..20 after 'between' in filter '{format}'")
+        low, low_open, high_open, high = match.groups()
+        bounds = (unquote(low.strip()), unquote(high.strip()), low_open == "<", high_open == "<")
+        return Filter(in_range, field, bounds, convert=False)
+
+    if " contains " in masked:
+        field, value = split(masked.index(" contains "), len(" contains "))
+        return Filter(array_contains, field, unquote(value), convert=False)
+
+    if " in " in masked:
+        value, field = split(masked.index(" in "), len(" in "))
+        return Filter(lambda a, b: b in a, field, unquote(value), ignore_case=ignore_case)
+
+    for operator, comp in FILTER_OPERATORS:
+        if operator not in masked:
+            continue
+        offset = masked.index(operator)
+        field, value = split(offset, len(operator))
+        if field == "":
+            raise InvalidFilter(f"empty field name before '{operator}' at offset {offset} in filter '{format}'")
+        if value == "":
+            raise InvalidFilter(f"empty value after '{operator}' at offset {offset} in filter '{format}'")
+        match = FIELD_FUNCTION_PATTERN.fullmatch(field)
+        if match is not None and match.group(1) not in FIELD_FUNCTIONS:
+            functions = ", ".join(FIELD_FUNCTIONS)
+            raise InvalidFilter(f"unknown function '{match.group(1)}' in filter '{format}' (expected {functions})")
+        return Filter(
+            comp, field, unquote(value),
+            stringify=operator in ("^=", "$="),
+            ignore_case=ignore_case and operator in ("^=", "$=", "!=", "="),
+            require_numeric=require_numeric and operator in ("<=", ">=", "<", ">") and is_number(value),
+        )
+
+    operators = ", ".join(operator for operator, _ in FILTER_OPERATORS)
+    raise InvalidFilter(f"expected operator ({operators}, in, contains, between) in filter '{format}'")
+
+
+HEATMAP_STYLES = ["red", "dark_orange", "yellow", "green_yellow", "green"]
+
+
+def heatmap_style(value, low, high, goal):
+    if not isinstance(value, (int, float)):
+        return None
+    score = 1.0 if high == low else (value - low) / (high - low)
+    if goal == "minimize":
+        score = 1.0 - score
+    return HEATMAP_STYLES[round(score * (len(HEATMAP_STYLES) - 1))]
+
+
+def compute_heatmap(experiments, metric):
+    values = [expe.get_field(metric) for expe in experiments]
+    numeric_values = [value for value in values if isinstance(value, (int, float))]
+    if len(numeric_values) == 0:
+        return {}
+    low, high = min(numeric_values), max(numeric_values)
+    return {
+        expe.id: {metric: heatmap_style(value, low, high, expe.get_goal(metric))}
+        for expe, value in zip(experiments, values)
+    }
+
+
+def compute_deltas(experiments, metric, baseline):
+    baseline_value = baseline.get_field(metric)
+    if not isinstance(baseline_value, (int, float)):
+        raise InvalidArguments(f"baseline {baseline.id[:7]} has no numeric value for '{metric}'")
+    goal = baseline.get_goal(metric)
+
+    deltas = {}
+    for expe in experiments:
+        value = expe.get_field(metric)
+        if not isinstance(value, (int, float)):
+            continue
+        delta = value - baseline_value
+        better = delta < 0 if goal == "minimize" else delta > 0
+        style = "green" if better else "red" if delta != 0 else "default"
+        deltas[expe.id] = f"[{style}]{delta:+.4g}[/{style}]"
+    return deltas
+
+
+SPARK_BLOCKS = "▁▂▃▄▅▆▇█"
+
+
+def sparkline(values):
+    if len(values) == 0:
+        return ""
+    low, high = min(values), max(values)
+    if high == low:
+        return SPARK_BLOCKS[0] * len(values)
+    scale = (len(SPARK_BLOCKS) - 1) / (high - low)
+    return "".join(SPARK_BLOCKS[round((value - low) * scale)] for value in values)
+
+
+def flatten(value, prefix=""):
+    if isinstance(value, dict) and len(value) > 0:
+        items = value.items()
+    elif isinstance(value, list) and len(value) > 0:
+        items = enumerate(value)
+    else:
+        return {prefix: value}
+
+    flat = {}
+    for key, item in items:
+        flat.update(flatten(item, f"{prefix}.{key}" if prefix else str(key)))
+    return flat
+
 
-    if " in " in format:
-        value, field = format.split(" in ")
-        field, value = field.strip(), value.strip()
-        return Filter(lambda a, b: b in a, field, value)
+def render_template(template, expe):
+    def replace(match):
+        if match.group(0) in ("{{", "}}"):
+            return match.group(0)[0]
+        value = expe.get_value(match.group(1).strip())
+        return format_cell(value)
 
-    if "!=" in format:
-        field, value = format.split("!=")
-        field, value = field.strip(), value.strip()
-        return Filter(lambda a, b: a != b, field, value)
+    return re.sub(r"\{\{|\}\}|\{([^{}]*)\}", replace, template)
 
-    if "<=" in format:
-        field, value = format.split("<=")
-        field, value = field.strip(), value.strip()
-        return Filter(lambda a, b: a <= b, field, value)
-    
-    if ">=" in format:
-        field, value = format.split(">=")
-        field, value = field.strip(), value.strip()
-        return Filter(lambda a, b: a >= b, field, value)
 
-    if "<" in format:
-        field, value = format.split("<")
-        field, value = field.strip(), value.strip()
-        return Filter(lambda a, b: a < b, field, value)
+def union_keys(dicts):
+    keys = []
+    for d in dicts:
+        keys += [key for key in d.keys() if key not in keys]
+    return keys
+
+
+def flatten_row(row):
+    flat = {}
+    for key, value in row.items():
+        if key in ("params", "config", "metrics") and isinstance(value, dict):
+            if len(value) > 0:
+                flat.update(flatten(value, key))
+        else:
+            flat[key] = value
+    return flat
+
+
+def csv_cell(value):
+    if isinstance(value, (dict, list)):
+        return json.dumps(value)
+    return format_cell(value)
+
+
+def write_csv(rows, no_header=False):
+    params = union_keys(row.get("params", {}) for row in rows)
+    metrics = union_keys(row.get("metrics", {}) for row in rows)
+    columns = union_keys({key: None for key in row if key not in ("params", "metrics")} for row in rows)
+    writer = csv.writer(sys.stdout)
+    if not no_header:
+        writer.writerow(columns + params + metrics)
+    for row in rows:
+        writer.writerow([csv_cell(value) for value in (
+            [row.get(key) for key in columns] +
+            [row["params"].get(key) for key in params] +
+            [row["metrics"].get(key) for key in metrics]
+        )])
+
+
+def fill_selected(rows, select):
+    # selected fields an experiment lacks are null, in the section where the others have them
+    params = union_keys(row["params"] for row in rows)
+    metrics = union_keys(row["metrics"] for row in rows)
+    for row in rows:
+        for name in select:
+            if name in row["params"] or name in row["metrics"] or name in row:
+                continue
+            if name in Experiment.BUILTIN_FIELDS and name not in params:
+                row[name] = None
+            elif name in metrics and name not in params:
+                row["metrics"][name] = None
+            else:
+                row["params"][name] = None
+        row["params"] = {name: row["params"][name] for name in select if name in row["params"]}
+        row["metrics"] = {name: row["metrics"][name] for name in select if name in row["metrics"]}
+    return rows
+
+
+def expand_rows(rows, experiments, null_as_missing=False):
+    params = union_keys(row["params"] for row in rows)
+    metrics = union_keys(row["metrics"] for row in rows)
+    for row, expe in zip(rows, experiments):
+        for field in Experiment.BUILTIN_FIELDS:
+            row[field] = expe.get_field(field)
+        if null_as_missing:
+            continue
+        row["params"] = {key: row["params"].get(key) for key in params}
+        row["metrics"] = {key: row["metrics"].get(key) for key in metrics}
+    return rows
+
+
+LIST_FORMATS = ["table", "json", "jsonl", "yaml", "csv"]
+
+
+def print_rows(rows, args):
+    if args.format == "json":
+        print(json.dumps(rows) if args.compact else json.dumps(rows, indent=2))
+    elif args.format == "jsonl":
+        for row in rows:
+            print(json.dumps(row))
+    elif args.format == "yaml":
+        print(yaml.safe_dump(rows, sort_keys=False), end="")
+    elif args.format == "csv":
+        write_csv(rows, args.no_header)
+
+
+def explain_exclusion(expe, raw_filter, filter):
+    explanation = f"excluded {expe.id[:7]}: {raw_filter}"
+    if isinstance(filter, Filter):
+        value, _ = filter.resolve(expe)
+        explanation += f" (had {value})"
+    print(explanation, file=sys.stderr)
+
+
+def sort_key(expe, field):
+    value = expe.get_field(field)
+    return value is None, value, expe.id
+
+
+def list_checkpoints(repo, experiments, args):
+    rows = []
+    for expe, checkpoint in repo.get_checkpoints(experiments):
+        metrics, _ = Experiment._select(checkpoint["metrics"], args.select)
+        rows.append({
+            "id": expe.id,
+            "step": checkpoint["step"],
+            "created": checkpoint["created"],
+            "path": expe.display_path(checkpoint.get("path")),
+            "metrics": dict(metrics),
+        })
+    if args.sort == "step":
+        rows = sorted(rows, key=lambda row: (row["step"], row["id"]))
+
+    if args.format != "table":
+        if args.flatten:
+            rows = [flatten_row(row) for row in rows]
+        print_rows(rows, args)
+        return
+
+    metrics = union_keys(row["metrics"] for row in rows)
+    table = Table(title="Checkpoints", box=box.ROUNDED, show_header=not args.no_header)
+    table.add_column("id", justify="center")
+    table.add_column("step", justify="right")
+    for metric in metrics:
+        table.add_column(metric, justify="right")
+    for row in rows:
+        table.add_row(
+            row["id"][:7],
+            str(row["step"]),
+            *[format_cell(row["metrics"].get(metric)) for metric in metrics],
+        )
+    Console().print(table)
 
-    if "=" in format:
-        field, value = format.split("=")
-        field, value = field.strip(), value.strip()
-        return Filter(lambda a, b: a == b, field, value)
 
-    if ">" in format:
-        field, value = format.split(">")
-        field, value = field.strip(), value.strip()
-        return Filter(lambda a, b: a > b, field, value)
+def list_deleted(repo, args):
+    rows = repo.get_tombstones()
+    if args.format != "table":
+        print_rows(rows, args)
+        return
 
-    raise Exception(f"invalid filter format '{format}'")
+    table = Table(title="Deleted experiments", box=box.ROUNDED, show_header=not args.no_header)
+    table.add_column("id", justify="center")
+    table.add_column("Deleted", justify="center")
+    table.add_column("Reason")
+    for row in rows:
+        deleted = display_time(datetime.fromisoformat(row["deleted"]))
+        table.add_row(row["id"][:7], deleted.strftime("%H:%M\n%D"), format_cell(row.get("reason")))
+    Console().print(table)
 
 
 def list_experiments(args):
     repo = KeepsakeRepository()
-    experiments = repo.get_experiments()
+    if args.format is None:
+        args.format = sake_setting("format", "table")
+        if args.format not in LIST_FORMATS:
+            formats = ", ".join(LIST_FORMATS)
+            raise InvalidArguments(f"invalid format '{args.format}' in .sake.toml, expected one of {formats}")
+    if args.deleted:
+        list_deleted(repo, args)
+        return
+    if args.view is not None:
+        args.select = (args.select or []) + load_view(args.view)
+    if args.out_template_file is not None:
+        if args.template is not None:
+            raise InvalidArguments("--template and --out-template-file cannot be combined")
+        with open(args.out_template_file) as f:
+            # print adds the newline ending each experiment
+            args.template = f.read().removesuffix("\n")
+    if args.min_checkpoints is not None:
+        args.filter = args.filter + [f"count(checkpoints)>={args.min_checkpoints}"]
+    all_fields = args.all_fields or (args.select is not None and "*" in args.select)
+    if all_fields:
+        args.select = None
+    experiments = repo.get_experiments(args.parallelism, args.duplicates, args.timeout)
+    # deleted experiments stay hidden if their metadata file lingers or is synced back
+    deleted = {tombstone["id"] for tombstone in repo.get_tombstones()}
+    experiments = [expe for expe in experiments if expe.id not in deleted]
+    for expe in experiments:
+        expe.at_step = args.at_step
+        expe.metric_agg = args.metric_agg
+        expe.coercions = dict(args.coerce)
+        expe.stale_after = args.stale_after
+
+    seen_path = repo.state_dir / "last-seen"
+    if args.reset_seen and seen_path.exists():
+        seen_path.unlink()
+    if args.since_last:
+        seen = set(seen_path.read_text().split()) if seen_path.exists() else set()
+        # every experiment in the repository is seen, even those hidden by other filters
+        seen_path.parent.mkdir(exist_ok=True)
+        seen_path.write_text("".join(f"{expe.id}\n" for expe in experiments))
+        experiments = [expe for expe in experiments if expe.id not in seen]
+
+    if args.since_experiment is not None:
+        reference = repo.get_experiment(args.since_experiment)
+        experiments = [expe for expe in experiments if expe.created > reference.created]
+
+    if args.id_glob is not None:
+        experiments = [expe for expe in experiments if fnmatch.fnmatchcase(expe.id, args.id_glob)]
+
+    if args.file_newer_than is not None:
+        cutoff = parse_cutoff(args.file_newer_than)
+        experiments = [
+            expe for expe in experiments
+            if datetime.fromtimestamp(expe.mtime) > cutoff
+        ]
 
-    filters = [compile_filter(raw_filter) for raw_filter in args.filter]
-    experiments = [
-        expe for expe in experiments if all(filter(expe) for filter in filters)
+    debug(f"loaded {len(experiments)} experiments")
+    filters = [
+        compile_filter(raw_filter, args.ignore_case, args.require_numeric)
+        for raw_filter in args.filter
     ]
-    if args.sort is not None:
-        experiments = sorted(experiments, key=lambda expe: expe.get_field(args.sort, 0.0))
+    for raw_filter in args.filter:
+        debug(f"filter: {raw_filter}")
+    kept = []
+    for expe in experiments:
+        # each filter runs once, --explain reports the first one that failed
+        failed = next((i for i, filter in enumerate(filters) if not filter(expe)), None)
+        if failed is None:
+            kept.append(expe)
+        elif args.explain:
+            explain_exclusion(expe, args.filter[failed], filters[failed])
+    experiments = kept
+    for command in args.filter_cmd:
+        experiments = CommandFilter(command).filter(experiments)
+    debug(f"{len(experiments)} experiments left after filtering")
+
+    if args.dedupe_by_command:
+        latest = {}
+        for expe in experiments:
+            if expe.command not in latest or expe.created > latest[expe.command].created:
+                latest[expe.command] = expe
+        experiments = list(latest.values())
+
+    if args.sample is not None:
+        # drawn from the experiments left after filtering, which are all loaded by then
+        experiments = random.Random(args.seed).sample(experiments, min(args.sample, len(experiments)))
+
+    if args.updated:
+        args.sort = "mtime"
+    if args.sort == "mtime":
+        experiments = sorted(experiments, key=lambda expe: (-expe.mtime, expe.id))
+    elif args.sort == "random":
+        # start from a fixed order so that a given seed always gives the same shuffle
+        experiments = sorted(experiments, key=lambda expe: expe.id)
+        random.Random(args.seed).shuffle(experiments)
+    elif args.sort is not None:
+        # experiments without a value for the field come last
+        experiments = sorted(experiments, key=lambda expe: sort_key(expe, args.sort))
     else:
-        experiments = sorted(experiments, key=lambda expe: expe.created)
+        experiments = sorted(experiments, key=lambda expe: (expe.created, expe.id))
+
+    if args.columns == "auto":
+        primary_names = {expe.get_primary_name() for expe in experiments} - {None}
+        args.select = varying_params(experiments, max_params=3) + sorted(primary_names)
 
     if args.quiet:
         for experiment in experiments:
             print(experiment.id)
         return
 
-    table = Table(title="Experiments", box=box.ROUNDED)
-    table.add_column("id", justify="center")
-    table.add_column("Created", justify="center")
-    table.add_column("Parameters")
-    table.add_column("Checkpoints")
+    if args.template is not None:
+        for expe in experiments:
+            print(render_template(args.template, expe))
+        return
+
+    if args.checkpoints_only:
+        list_checkpoints(repo, experiments, args)
+        return
+
+    if args.format != "table":
+        rows = [expe.to_row(args.select, args.null_as_missing) for expe in experiments]
+        if all_fields:
+            rows = expand_rows(rows, experiments, args.null_as_missing)
+        elif args.select is not None and not args.null_as_missing:
+            rows = fill_selected(rows, args.select)
+        if args.flatten:
+            rows = [flatten_row(row) for row in rows]
+        print_rows(rows, args)
+        return
 
+    heatmap = {}
+    if args.heatmap is not None:
+        heatmap = compute_heatmap(experiments, args.heatmap)
+
+    deltas = {}
+    if args.metric_delta is not None:
+        if args.baseline is None:
+            raise InvalidArguments("--metric-delta requires --baseline")
+        deltas = compute_deltas(experiments, args.metric_delta, repo.get_experiment(args.baseline))
+
+    tags = repo.get_tags()
+    show_tags = any(expe.id in tags for expe in experiments)
+    # selected built-in fields get their own column, the other fields stay in params and metrics
+    select = args.select
+    builtins = [name for name in select or [] if name in Experiment.BUILTIN_FIELDS]
+    if len(builtins) > 0:
+        select = [name for name in select if name not in builtins]
+    show_values = select is None or len(select) > 0
+    columns = [("id", "center"), ("Created", "center")]
+    if show_values:
+        columns += [("Parameters", "left"), ("Checkpoints", "left")]
+    columns += [(name, "left") for name in builtins]
+    if show_tags:
+        columns.insert(1, ("Tag", "left"))
+    if args.with_checkpoints_count:
+        columns.append(("#", "left"))
+    if args.metric_delta is not None:
+        columns.append((f"Δ {args.metric_delta}", "right"))
+    if args.spark is not None:
+        columns.append((args.spark, "left"))
+
+    rows = []
     for expe in experiments:
-        table.add_row(
+        cells = [
             expe.id[:7],
-            expe.created.strftime("%H:%M\n%D"),
-            expe.get_params(args.select),
-            expe.get_metrics(args.select),
-        )
+            format_relative_time(expe.created) if args.relative_time else display_time(expe.created).strftime("%H:%M\n%D"),
+        ]
+        if show_values:
+            cells += [
+                expe.get_params(select, show_all=all_fields),
+                expe.get_metrics(select, show_all=all_fields, styles=heatmap.get(expe.id, {})),
+            ]
+        cells += [format_field(name, expe.get_field(name)) for name in builtins]
+        if show_tags:
+            cells.insert(1, format_cell(tags.get(expe.id)))
+        if args.with_checkpoints_count:
+            cells.append(str(expe.get_n_checkpoints()))
+        if args.metric_delta is not None:
+            cells.append(deltas.get(expe.id, format_cell(None)))
+        if args.spark is not None:
+            history = [value for _, value in expe.get_metric_history(args.spark)]
+            cells.append(sparkline(history) if len(history) > 0 else format_cell(None))
+        rows.append(cells)
+
+    alignments = {column.lower(): justify for column, justify in args.align}
+    table = Table(title="Experiments", box=box.ROUNDED, show_header=not args.no_header)
+    for (column, _), justify in zip(columns, column_justify(columns, rows, alignments)):
+        table.add_column(column, justify=justify)
+    for row in rows:
+        table.add_row(*row)
 
     console = Console()
     if len(experiments) > 5:
@@ -301,18 +1728,280 @@ def list_experiments(args):
         console.print(table)
 
 
+def column_justify(columns, rows, alignments):
+    # value columns holding only numbers are right-aligned, ids like 1234567 keep their alignment
+    justify = []
+    for i, (column, default) in enumerate(columns):
+        values = [row[i] for row in rows if row[i] not in ("", na_string)]
+        if (column not in ("id", "Tag", "Created") and len(values) > 0
+                and all(isinstance(value, str) and is_number(value) for value in values)):
+            default = "right"
+        justify.append(alignments.get(column.lower(), default))
+    return justify
+
+
+def varying_params(experiments, max_params=2):
+    # the params with the most distinct values first
+    keys = union_keys(expe.params for expe in experiments)
+    counts = {
+        key: len(set(json.dumps(expe.params.get(key), sort_keys=True) for expe in experiments))
+        for key in keys
+    }
+    varying = sorted((key for key in keys if counts[key] > 1), key=lambda key: -counts[key])
+    return varying[:max_params]
+
+
+def top_experiments(args):
+    repo = KeepsakeRepository()
+    experiments = repo.get_experiments()
+
+    filters = [compile_filter(raw_filter) for raw_filter in args.filter]
+    experiments = [
+        expe for expe in experiments
+        if all(filter(expe) for filter in filters)
+        and isinstance(expe.get_field(args.metric), (int, float))
+    ]
+
+    goals = [expe.get_goal(args.metric) for expe in experiments]
+    goal = next((goal for goal in goals if goal is not None), "maximize")
+    experiments = sorted(
+        experiments,
+        key=lambda expe: expe.get_field(args.metric),
+        reverse=goal == "maximize",
+    )[:args.limit]
+
+    params = varying_params(experiments)
+    table = Table(title=f"Top {args.metric}", box=box.ROUNDED)
+    table.add_column("#", justify="right")
+    table.add_column("id", justify="center")
+    table.add_column(args.metric, justify="right")
+    for param in params:
+        table.add_column(param)
+    for rank, expe in enumerate(experiments):
+        table.add_row(
+            str(rank + 1),
+            expe.id[:7],
+            str(expe.get_field(args.metric)),
+            *[format_cell(expe.params.get(param)) for param in params],
+        )
+    Console().print(table)
+
+
+def group_sort_key(value):
+    # numeric groups come first in numeric order, then the others as strings
+    if isinstance(value, (int, float)) and not isinstance(value, bool):
+        return (0, value, "")
+    return (1, 0, str(value))
+
+
+def metric_history(args):
+    repo = KeepsakeRepository()
+    experiments = repo.get_experiments()
+
+    filters = [compile_filter(raw_filter) for raw_filter in args.filter]
+    experiments = [
+        expe for expe in experiments
+        if all(filter(expe) for filter in filters)
+        and isinstance(expe.get_field(args.metric), (int, float))
+    ]
+
+    goals = [expe.get_goal(args.metric) for expe in experiments]
+    goal = next((goal for goal in goals if goal is not None), "maximize")
+    best = max if goal == "maximize" else min
+
+    groups = {}
+    for expe in experiments:
+        key = expe.get_field(args.group_by)
+        if isinstance(key, (list, dict)):
+            key = json.dumps(key)
+        groups.setdefault(key, []).append(expe)
+
+    table = Table(title=f"Best {args.metric} by {args.group_by}", box=box.ROUNDED)
+    table.add_column(args.group_by)
+    table.add_column(args.metric, justify="right")
+    table.add_column("id", justify="center")
+    table.add_column("#", justify="right")
+    for key in sorted(groups, key=group_sort_key):
+        best_expe = best(groups[key], key=lambda expe: expe.get_field(args.metric))
+        table.add_row(
+            format_cell(key),
+            str(best_expe.get_field(args.metric)),
+            best_expe.id[:7],
+            str(len(groups[key])),
+        )
+    Console().print(table)
+
+
+def require_checkpoint(expe, step):
+    checkpoint = expe.get_checkpoint_at_step(step)
+    if checkpoint is None:
+        steps = ", ".join(str(checkpoint["step"]) for checkpoint in expe.checkpoints or [])
+        raise MissingCheckpoint(f"experiment {expe.id[:7]} has no checkpoint at step {step} "
+                                f"(available steps: {steps or 'none'})")
+    return checkpoint
+
+
+def show_checkpoint(expe, step):
+    checkpoint = require_checkpoint(expe, step)
+
+    primary_metric = checkpoint["primary_metric"]
+    details = "\n".join([
+        f"created: {checkpoint['created']}",
+        f"path: {expe.display_path(checkpoint['path'])}",
+        f"primary metric: {primary_metric['name']} ({primary_metric['goal']})",
+    ])
+    metrics = "\n".join(
+        f"{key}: {value}{expe.get_trend_arrow(checkpoint, key)}"
+        for key, value in checkpoint["metrics"].items()
+    )
+
+    console = Console()
+    console.print(Panel(RenderGroup(
+        Panel(details, box=box.SIMPLE, title="Checkpoint"),
+        Panel(metrics, box=box.SIMPLE, title="Metrics"),
+    ), box=box.SIMPLE, title=f"Experiment {expe.id[:7]} at step {step}"))
+
+
+def show_fields(expe):
+    fields = {"id": expe.id, "created": display_time(expe.created), "command": expe.command}
+    fields.update({field: expe.get_field(field) for field in Experiment.BUILTIN_FIELDS})
+    if fields["duration"] is not None:
+        fields["duration"] = format_duration(fields["duration"])
+    fields.update(flatten(expe.params))
+    checkpoint = expe.get_displayed_checkpoint()
+    if checkpoint is not None:
+        fields["step"] = checkpoint["step"]
+        fields.update(checkpoint["metrics"])
+
+    table = Table(title=f"Experiment {expe.id[:7]}", box=box.ROUNDED)
+    table.add_column("Field")
+    table.add_column("Value")
+    for field, value in fields.items():
+        table.add_row(field, format_cell(value))
+    Console().print(table)
+
+
 def show_experiment(args):
     repo = KeepsakeRepository()
+    if args.view is not None:
+        args.select = (args.select or []) + load_view(args.view)
     expe = repo.get_experiment(args.id)
 
+    if args.raw_metrics:
+        if args.checkpoint is not None:
+            checkpoint = require_checkpoint(expe, args.checkpoint)
+        else:
+            checkpoint = expe.get_displayed_checkpoint()
+            if checkpoint is None:
+                raise MissingCheckpoint(f"experiment {expe.id[:7]} has no checkpoints")
+        print(json.dumps(checkpoint["metrics"], indent=2))
+        return
+
+    if args.checkpoint is not None:
+        show_checkpoint(expe, args.checkpoint)
+        return
+
+    if args.table:
+        show_fields(expe)
+        return
+
+    duration = expe.get_duration()
+    duration = "no checkpoints" if duration is None else format_duration(duration)
+
     console = Console()
     console.print(Panel(RenderGroup(
         Panel(f"python {expe.command}", box=box.SIMPLE, title="Command"),
-        Panel(expe.get_params(args.select if not args.show_all else None, args.show_all), box=box.SIMPLE, title="Parameters"),
-        Panel(expe.get_metrics(args.select if not args.show_all else None, args.show_all), box=box.SIMPLE, title="Checkpoint")
+        Panel(duration, box=box.SIMPLE, title="Duration"),
+        Panel(expe.get_params(args.select if not args.show_all else None, args.show_all, pretty=True), box=box.SIMPLE, title="Parameters"),
+        Panel(expe.get_metrics(args.select if not args.show_all else None, args.show_all, trends=True), box=box.SIMPLE, title="Checkpoint")
     ), box=box.SIMPLE, title=f"Experiment {expe.id[:7]}"))
 
 
+def compare_metrics(expe1, expe2, keys):
+    table = Table(title="Metrics", box=box.ROUNDED)
+    table.add_column("Metric")
+    table.add_column(expe1.id[:7] + f" (step {expe1.get_best_step()})")
+    table.add_column(expe2.id[:7] + f" (step {expe2.get_best_step()})")
+    table.add_column("Delta")
+    table.add_column("Better")
+    for key in sorted(keys):
+        value1, value2 = expe1.get_field(key), expe2.get_field(key)
+        delta, better = format_cell(None), format_cell(None)
+        if isinstance(value1, (int, float)) and isinstance(value2, (int, float)):
+            delta = f"{value2 - value1:+g}"
+            goal = expe1.get_goal(key) or expe2.get_goal(key)
+            if value1 == value2:
+                better = "="
+            elif goal is not None:
+                first_better = (value1 > value2) == (goal == "maximize")
+                better = expe1.id[:7] if first_better else expe2.id[:7]
+                better = f"[green]{better}[/green]"
+        table.add_row(key, format_cell(value1), format_cell(value2), delta, better)
+    return table
+
+
+def compare_values(title, label, expe1, expe2, values1, values2, show_all=False):
+    table = Table(title=title, box=box.ROUNDED)
+    table.add_column(label)
+    table.add_column(expe1.id[:7])
+    table.add_column(expe2.id[:7])
+    for key in sorted(set(values1).union(values2)):
+        value1, value2 = values1.get(key), values2.get(key)
+        if value1 != value2 or show_all:
+            table.add_row(key, format_cell(value1), format_cell(value2))
+    return table
+
+
+def compare_environments(expe1, expe2, show_all=False):
+    table = Table(title="Environment", box=box.ROUNDED)
+    table.add_column("Package")
+    table.add_column(expe1.id[:7])
+    table.add_column(expe2.id[:7])
+
+    version1, version2 = expe1.json.get("python_version"), expe2.json.get("python_version")
+    if version1 != version2 or show_all:
+        table.add_row("python", format_cell(version1), format_cell(version2))
+
+    packages1 = expe1.json.get("python_packages") or {}
+    packages2 = expe2.json.get("python_packages") or {}
+    for package in sorted(set(packages1).union(packages2)):
+        version1, version2 = packages1.get(package), packages2.get(package)
+        if version1 != version2 or show_all:
+            table.add_row(package, format_cell(version1), format_cell(version2))
+    return table
+
+
+def tag_experiment(args):
+    repo = KeepsakeRepository()
+    expe = repo.get_experiment(args.id)
+    repo.set_tag(expe, args.tag)
+
+
+def cat_experiment(args):
+    repo = KeepsakeRepository()
+    expe = repo.get_experiment(args.id)
+    with open(expe.file_path, "rb") as f:
+        sys.stdout.buffer.write(f.read())
+
+
+def grep_experiments(args):
+    repo = KeepsakeRepository()
+    if args.regex:
+        try:
+            matches = re.compile(args.pattern).search
+        except re.error as e:
+            raise InvalidArguments(f"invalid regex '{args.pattern}' ({e})")
+    else:
+        matches = lambda line: args.pattern in line
+
+    for file_path in repo._get_experiments_files():
+        expe_id = Path(file_path).stem
+        with open(file_path, errors="replace") as f:
+            for line in f:
+                if matches(line):
+                    print(f"{expe_id[:7]}: {line.strip()}")
+
+
 def diff_experiments(args):
     repo = KeepsakeRepository()
     expe1 = repo.get_experiment(args.id1)
@@ -324,36 +2013,33 @@ def diff_experiments(args):
 
     keys = get_keys(expe1._get_metrics()).union(get_keys(expe2._get_metrics()))
 
-    def get_key(d, k, v=None):
-        try:
-            return d[k]
-        except:
-            return v
+    if args.metrics:
+        console.print(compare_metrics(expe1, expe2, keys))
+        return
 
-    params = set(expe1.params.keys()).union(set(expe2.params.keys()))
+    if args.env:
+        console.print(compare_environments(expe1, expe2))
+        return
 
-    table = Table(title="Params", box=box.ROUNDED)
-    table.add_column("Parameter")
-    table.add_column(expe1.id[:7])
-    table.add_column(expe2.id[:7])
-    for param in params:
-        value1 = get_key(expe1.params, param, None) 
-        value2 =  get_key(expe2.params, param, None)
-        if value1 != value2:
-            table.add_row(param, str(value1), str(value2))
-    console.print(table)
+    params1, params2 = expe1.params or {}, expe2.params or {}
+    console.print(compare_values("Params", "Parameter", expe1, expe2, params1, params2, args.all))
+
+    config1, config2 = expe1.json.get("config") or {}, expe2.json.get("config") or {}
+    console.print(compare_values("Config", "Key", expe1, expe2, config1, config2, args.all))
 
     table = Table(title="Metrics", box=box.ROUNDED)
     table.add_column("Metric")
     table.add_column(expe1.id[:7] + f" (step {expe1.get_best_step()})")
     table.add_column(expe2.id[:7] + f" (step {expe2.get_best_step()})")
-    for key in keys:
+    for key in sorted(keys):
         value1, value2 = expe1.get_field(key), expe2.get_field(key)
-        if value1 != value2:
-            table.add_row(key, str(value1), str(value2))
-
+        if value1 != value2 or args.all:
+            table.add_row(key, format_cell(value1), format_cell(value2))
     console.print(table)
 
+    console.print(compare_environments(expe1, expe2, args.all))
+
+
 def reproduce_experiment(args):
     repo = KeepsakeRepository()
     expe = repo.get_experiment(args.id)
@@ -373,26 +2059,385 @@ def reproduce_experiment(args):
     os.system(command)
 
 
+def write_checkpoints_csv(f, expe, no_header=False):
+    checkpoints = expe.checkpoints or []
+    keys = union_keys(checkpoint["metrics"] for checkpoint in checkpoints)
+
+    writer = csv.writer(f)
+    if not no_header:
+        writer.writerow(["step", "created"] + keys)
+    for checkpoint in checkpoints:
+        metrics = checkpoint["metrics"]
+        writer.writerow(
+            [checkpoint["step"], checkpoint["created"]] +
+            [format_cell(metrics.get(key)) for key in keys]
+        )
+
+
+def export_experiments(repo, args):
+    filters = [compile_filter(raw_filter) for raw_filter in args.filter]
+    experiments = [
+        expe for expe in repo.get_experiments()
+        if all(filter(expe) for filter in filters)
+    ]
+
+    output_dir = Path(args.output_dir)
+    output_dir.mkdir(parents=True, exist_ok=True)
+    for expe in experiments:
+        if not expe.checkpoints:
+            warn(f"experiment {expe.id[:7]} has no checkpoints, skipping")
+            continue
+        with open(output_dir / f"{expe.id[:7]}.csv", "w", newline="") as f:
+            write_checkpoints_csv(f, expe, args.no_header)
+
+
+def export_experiment(args):
+    repo = KeepsakeRepository()
+    if args.output_dir is not None:
+        if args.id is not None or args.output is not None:
+            raise InvalidArguments("--output-dir cannot be combined with an id or --output")
+        export_experiments(repo, args)
+        return
+    if args.id is None:
+        raise InvalidArguments("an experiment id is required unless --output-dir is given")
+    if len(args.filter) > 0:
+        raise InvalidArguments("--filter can only be used with --output-dir")
+
+    expe = repo.get_experiment(args.id)
+    f = open(args.output, "w", newline="") if args.output is not None else sys.stdout
+    try:
+        write_checkpoints_csv(f, expe, args.no_header)
+    finally:
+        if f is not sys.stdout:
+            f.close()
+
+
+def init_repository(args):
+    config_path = Path("keepsake.yml")
+    if config_path.exists() and not args.force:
+        raise InvalidArguments("keepsake.yml already exists, use --force to overwrite it")
+    location = KeepsakeRepository(args.repo).location
+
+    (location / "metadata/experiments").mkdir(parents=True, exist_ok=True)
+    with open(config_path, "w") as f:
+        f.write(f'repository: "{args.repo}"\n')
+    Console().print(f"Initialized keepsake repository in [cyan]{location}[/cyan]")
+
+
+def show_config(args):
+    repo = KeepsakeRepository()
+    config_source = repo.config_path.name
+    try:
+        sake_config = load_sake_config()
+    except ImportError as e:
+        warn(f"{e}, ignoring its settings")
+        sake_config = {}
+
+    settings = [
+        ("config file", str(repo.config_path), "discovered"),
+        ("repository", repo.config["repository"], config_source),
+        ("location", str(repo.location), config_source),
+        ("storage", str(repo.storage or "file"), config_source if repo.storage else "default"),
+        ("parallelism", os.environ.get("SAKE_THREADS", "auto"), "env" if "SAKE_THREADS" in os.environ else "default"),
+        ("format", sake_config.get("format", "table"), ".sake.toml" if "format" in sake_config else "default"),
+        ("views", ", ".join(sake_config.get("views", {})) or "none", ".sake.toml" if "views" in sake_config else "default"),
+        ("prefer", args.prefer, "flag" if args.prefer != "params" else "default"),
+        ("na string", repr(args.na_string), "flag" if args.na_string != "" else "default"),
+        ("dates", "local" if local_dates else "UTC",
+         "flag" if args.local else ".sake.toml" if local_dates else "default"),
+    ]
+
+    table = Table(title="Configuration", box=box.ROUNDED)
+    table.add_column("Setting")
+    table.add_column("Value")
+    table.add_column("Source")
+    for setting in settings:
+        table.add_row(*setting)
+    Console().print(table)
+
+
+def check_type(problems, values, key, types, where, required=True):
+    if key not in values:
+        if required:
+            problems.append(f"{where}missing '{key}'")
+        return False
+    if not isinstance(values[key], types):
+        expected = " or ".join("null" if t is type(None) else t.__name__ for t in types)
+        problems.append(f"{where}'{key}' should be {expected}, got {type(values[key]).__name__}")
+        return False
+    return True
+
+
+def validate_experiment_json(expe_json):
+    if not isinstance(expe_json, dict):
+        return [f"expected an object, got {type(expe_json).__name__}"]
+
+    problems = []
+    check_type(problems, expe_json, "id", (str,), "")
+    if check_type(problems, expe_json, "created", (str,), ""):
+        try:
+            parse_timestamp(expe_json["created"])
+        except ValueError:
+            problems.append(f"invalid 'created' timestamp '{expe_json['created']}'")
+    check_type(problems, expe_json, "params", (dict, type(None)), "")
+    check_type(problems, expe_json, "command", (str,), "")
+    if not check_type(problems, expe_json, "checkpoints", (list, type(None)), ""):
+        return problems
+
+    for i, checkpoint in enumerate(expe_json["checkpoints"] or []):
+        where = f"checkpoint {i}: "
+        if not isinstance(checkpoint, dict):
+            problems.append(f"{where}expected an object, got {type(checkpoint).__name__}")
+            continue
+        check_type(problems, checkpoint, "id", (str,), where)
+        check_type(problems, checkpoint, "step", (int,), where)
+        check_type(problems, checkpoint, "created", (str,), where)
+        check_type(problems, checkpoint, "metrics", (dict,), where)
+        if check_type(problems, checkpoint, "primary_metric", (dict,), where):
+            check_type(problems, checkpoint["primary_metric"], "name", (str,), where + "primary_metric ")
+            check_type(problems, checkpoint["primary_metric"], "goal", (str,), where + "primary_metric ")
+    return problems
+
+
+def validate_repository(args):
+    repo = KeepsakeRepository()
+    n_invalid = 0
+    experiment_files = repo._get_experiments_files()
+    for file_path in experiment_files:
+        try:
+            with open(file_path) as f:
+                problems = validate_experiment_json(json.load(f))
+        except ValueError as e:
+            problems = [f"invalid json ({e})"]
+        if len(problems) == 0:
+            debug(f"ok: {file_path}")
+            continue
+        n_invalid += 1
+        print(f"{file_path}:")
+        for problem in problems:
+            print(f"  {problem}")
+
+    print(f"{len(experiment_files) - n_invalid} valid, {n_invalid} invalid")
+    if n_invalid > 0:
+        sys.exit(1)
+
+
+def disk_size(path):
+    if path.is_file():
+        return path.stat().st_size
+    return sum(child.stat().st_size for child in path.rglob("*") if child.is_file())
+
+
+def confirm_deletion(repo, experiments, yes=False, dry_run=False):
+    size = sum(
+        disk_size(path)
+        for expe in experiments
+        for path in repo.get_stored_files(expe)
+    )
+
+    console = Console()
+    console.print(f"{len(experiments)} experiment(s) will be deleted, freeing {format_size(size)}:")
+    for expe in experiments:
+        console.print(f"  [cyan]{expe.id[:7]}[/cyan]")
+
+    if dry_run:
+        return False
+    if yes:
+        return True
+    return Confirm.ask("Do you want to delete them?")
+
+
+def delete_experiments(args):
+    repo = KeepsakeRepository()
+    experiments = []
+    for expe_id in args.ids:
+        if not is_glob(expe_id):
+            experiments.append(repo.get_experiment(expe_id))
+            continue
+        matches = repo.find_experiments(expe_id)
+        if len(matches) > 1 and not args.yes:
+            raise AmbiguousExperiment(f"'{expe_id}' matches {len(matches)} experiments, use --yes to delete them all")
+        experiments += matches
+    # an experiment matched by several arguments is deleted once
+    experiments = list({expe.id: expe for expe in experiments}.values())
+
+    if not confirm_deletion(repo, experiments, args.yes, args.dry_run):
+        return
+
+    for expe in experiments:
+        repo.add_tombstone(expe, args.reason)
+        repo.remove_tag(expe)
+        for path in repo.get_stored_files(expe):
+            if path.is_dir():
+                shutil.rmtree(path)
+            else:
+                path.unlink()
+
+
 def parse_args():
     parser = argparse.ArgumentParser("sake")
+    parser.add_argument("-v", "--verbose", action="store_true",
+                        help="print diagnostics on stderr")
+    parser.add_argument("--na-string", default="", metavar="TEXT",
+                        help="text displayed for missing values in tables and csv (default: empty)")
+    parser.add_argument("--local", action="store_true",
+                        help="display and parse dates in local time instead of UTC "
+                             "(or set local = true in .sake.toml)")
+    parser.add_argument("--absolute-paths", action="store_true",
+                        help="print checkpoint paths joined with the repository location")
+    parser.add_argument("--strip-ansi", action="store_true",
+                        help="remove any terminal escape codes from the output, even on a terminal")
+    parser.add_argument("--fail-on-warning", action="store_true",
+                        help="exit with a non-zero code if any warning was printed")
+    parser.add_argument("--prefer", choices=["params", "metrics"], default="params",
+                        help="where to look first for a field defined both as a param and a metric")
+    parser.add_argument("--error-format", choices=["human", "json"], default="human",
+                        help="how errors are reported on stderr")
     commands = parser.add_subparsers()
 
     ls = commands.add_parser("list", aliases=["ls"])
-    ls.add_argument("-f", "--filter", default=[], action="append")
-    ls.add_argument("-s", "--select", action="append")
+    ls.add_argument("-f", "--filter", default=[], action="append",
+                    help="condition to match, like lr<0.1, combined with and, or, not and parentheses, "
+                         "improvement(METRIC) is only defined for primary metrics")
+    ls.add_argument("--min-checkpoints", type=int, metavar="N",
+                    help="only list experiments with at least N checkpoints")
+    ls.add_argument("--as", dest="coerce", type=parse_coercion, default=[], action="append",
+                    metavar="FIELD:TYPE", help="convert a field to number, bool or string before filtering")
+    ls.add_argument("--explain", action="store_true",
+                    help="print on stderr the filter that excluded each experiment")
+    ls.add_argument("-i", "--ignore-case", action="store_true",
+                    help="compare strings case-insensitively in =, !=, in, ^= and $= filters")
+    ls.add_argument("--require-numeric", action="store_true",
+                    help="warn about experiments without a numeric value in <, <=, > and >= filters")
+    ls.add_argument("--filter-cmd", default=[], action="append", metavar="CMD",
+                    help="keep experiments for which CMD exits with 0 when given "
+                         "the experiment JSON on stdin (spawns one process per experiment)")
+    ls.add_argument("-s", "--select", action="append", help="fields to display, '*' for all of them")
+    ls.add_argument("--columns", choices=["auto"],
+                    help="auto selects the primary metrics and the params that vary the most")
+    ls.add_argument("--all-fields", action="store_true", help="same as --select '*'")
+    ls.add_argument("--view", help="file listing the fields to select, one per line, "
+                                   "or the name of a view from .sake.toml")
     ls.add_argument("-q", "--quiet", action="store_true", help="return only the ids")
-    ls.add_argument("--sort")
+    ls.add_argument("--sort", help="field to sort by, 'random' to shuffle or 'mtime' for the most "
+                                   "recently modified first (experiments with equal values are ordered by id)")
+    ls.add_argument("--updated", action="store_true", help="same as --sort mtime")
+    ls.add_argument("--sample", type=int, metavar="N", help="only list N experiments picked at random")
+    ls.add_argument("--seed", type=int, help="seed for --sort random and --sample")
+    # a string default goes through parse_parallelism only when list is run
+    ls.add_argument("--parallelism", type=parse_parallelism, metavar="N",
+                    default=os.environ.get("SAKE_THREADS"),
+                    help="number of threads used to load experiments, 1 loads them sequentially "
+                         "(default: $SAKE_THREADS or auto)")
+    ls.add_argument("--format", choices=LIST_FORMATS,
+                    help="output format (default: the format setting of .sake.toml, or table)")
+    ls.add_argument("--template", help="print each experiment with a format string, "
+                                       "e.g. '{id} lr={lr}' ('{{' and '}}' for literal braces)")
+    ls.add_argument("--out-template-file", metavar="FILE",
+                    help="like --template, with the format string read from FILE")
+    ls.add_argument("--with-checkpoints-count", action="store_true",
+                    help="add a column with the number of checkpoints")
+    ls.add_argument("--relative-time", action="store_true",
+                    help="show creation times relative to now in the table, e.g. '2 hours ago'")
+    ls.add_argument("--no-header", action="store_true",
+                    help="do not print the header row of table and csv output")
+    ls.add_argument("--flatten", action="store_true",
+                    help="use dotted keys instead of nested params, config and metrics")
+    ls.add_argument("--compact", action="store_true",
+                    help="print json output on a single line")
+    ls.add_argument("--align", type=parse_alignment, default=[], action="append", metavar="COLUMN:JUSTIFY",
+                    help="justify a table column (left, center or right), numeric columns are right-aligned by default")
+    ls.add_argument("--null-as-missing", action="store_true",
+                    help="omit absent fields from machine-readable output instead of writing null, "
+                         "explicit null values are kept")
+    ls.add_argument("--checkpoints-only", action="store_true",
+                    help="list one row per checkpoint instead of one per experiment")
+    ls.add_argument("--at-step", type=int, metavar="STEP",
+                    help="read metrics from the checkpoint at STEP instead of the best one")
+    ls.add_argument("--metric-agg", choices=["best", *METRIC_AGGREGATIONS],
+                    help="how metrics are reduced over the checkpoints, best follows the goal of the "
+                         "primary metric and takes the max of the others (default: read them from "
+                         "the best checkpoint)")
+    ls.add_argument("--stale-after", type=parse_duration, default=timedelta(days=1), metavar="DURATION",
+                    help="age of the latest checkpoint after which the 'state' field of "
+                         "an experiment is 'stale' instead of 'active' (default: 1d)")
+    ls.add_argument("--timeout", type=parse_duration, metavar="DURATION",
+                    help="fail if loading the experiments takes longer than this")
+    ls.add_argument("--duplicates", choices=["first", "latest"], default="first",
+                    help="which file to keep when several define the same experiment id: "
+                         "the first by file name or the latest modified")
+    ls.add_argument("--id-glob", metavar="PATTERN", help="only list experiments whose id matches PATTERN")
+    ls.add_argument("--file-newer-than", metavar="DURATION|DATE",
+                    help="only list experiments whose metadata file was modified after this "
+                         "date or within this duration, e.g. 12h")
+    ls.add_argument("--since-experiment", metavar="ID",
+                    help="only list experiments created after this one")
+    ls.add_argument("--deleted", action="store_true",
+                    help="list the experiments deleted with rm instead")
+    ls.add_argument("--since-last", action="store_true",
+                    help="only list experiments added since the previous --since-last run")
+    ls.add_argument("--reset-seen", action="store_true",
+                    help="forget the experiments recorded by --since-last")
+    ls.add_argument("--dedupe-by-command", action="store_true",
+                    help="only keep the latest experiment for each command")
+    ls.add_argument("--heatmap", metavar="METRIC",
+                    help="color a metric from worst (red) to best (green)")
+    ls.add_argument("--metric-delta", metavar="METRIC",
+                    help="show the difference of a metric with the --baseline experiment")
+    ls.add_argument("--spark", metavar="METRIC", help="show the history of a metric as a sparkline")
+    ls.add_argument("--baseline", metavar="ID", help="experiment compared against by --metric-delta")
     ls.set_defaults(func=list_experiments)
 
+    top = commands.add_parser("top")
+    top.add_argument("metric")
+    top.add_argument("-f", "--filter", default=[], action="append")
+    top.add_argument("-n", "--limit", type=int, default=10, help="number of experiments to show")
+    top.set_defaults(func=top_experiments)
+
+    history = commands.add_parser("history")
+    history.add_argument("metric")
+    history.add_argument("-g", "--group-by", required=True, metavar="FIELD",
+                         help="field whose values define the groups")
+    history.add_argument("-f", "--filter", default=[], action="append")
+    history.set_defaults(func=metric_history)
+
     show = commands.add_parser("show")
     show.add_argument("id")
     show.add_argument("-s", "--select", action="append")
+    show.add_argument("--view", help="file listing the fields to select, one per line, "
+                                     "or the name of a view from .sake.toml")
     show.add_argument("-a", "--show-all", action="store_true")
+    show.add_argument("-t", "--table", action="store_true",
+                      help="show one field per row, nested params are flattened")
+    show.add_argument("--checkpoint", type=int, metavar="STEP",
+                      help="only show the checkpoint at STEP")
+    show.add_argument("--raw-metrics", action="store_true",
+                      help="print the metrics of the checkpoint as json")
     show.set_defaults(func=show_experiment)
 
+    tag = commands.add_parser("tag")
+    tag.add_argument("id")
+    tag.add_argument("tag", help="unique label that can be used instead of the id")
+    tag.set_defaults(func=tag_experiment)
+
+    cat = commands.add_parser("cat")
+    cat.add_argument("id")
+    cat.set_defaults(func=cat_experiment)
+
+    grep = commands.add_parser("grep")
+    grep.add_argument("pattern")
+    grep.add_argument("--regex", action="store_true", help="interpret the pattern as a regular expression")
+    grep.set_defaults(func=grep_experiments)
+
     diff = commands.add_parser("diff")
     diff.add_argument("id1")
     diff.add_argument("id2")
+    diff.add_argument("--metrics", action="store_true",
+                      help="compare the best checkpoint metrics with their delta")
+    diff.add_argument("--env", action="store_true",
+                      help="compare the python version and packages")
+    diff.add_argument("-a", "--all", action="store_true",
+                      help="also show the keys that are identical")
     diff.set_defaults(func=diff_experiments)
 
     repr_parser = commands.add_parser("repr", aliases=["reproduce"])
@@ -401,6 +2446,33 @@ def parse_args():
                              help="do not ask for confirmation")
     repr_parser.set_defaults(func=reproduce_experiment)
 
+    rm = commands.add_parser("rm", aliases=["remove"])
+    rm.add_argument("ids", nargs="+", metavar="id")
+    rm.add_argument("-y", "--yes", action="store_true", help="do not ask for confirmation")
+    rm.add_argument("--dry-run", action="store_true", help="only print what would be deleted")
+    rm.add_argument("--reason", help="why the experiments are deleted, shown by list --deleted")
+    rm.set_defaults(func=delete_experiments)
+
+    init = commands.add_parser("init")
+    init.add_argument("--repo", default="file://.keepsake",
+                      help="repository location (default: file://.keepsake)")
+    init.add_argument("--force", action="store_true", help="overwrite an existing keepsake.yml")
+    init.set_defaults(func=init_repository)
+
+    validate = commands.add_parser("validate")
+    validate.set_defaults(func=validate_repository)
+
+    config = commands.add_parser("config")
+    config.set_defaults(func=show_config)
+
+    export = commands.add_parser("export")
+    export.add_argument("id", nargs="?")
+    export.add_argument("-o", "--output", help="csv file to write, defaults to stdout")
+    export.add_argument("--output-dir", help="write one csv per matching experiment to this directory")
+    export.add_argument("-f", "--filter", default=[], action="append")
+    export.add_argument("--no-header", action="store_true", help="do not print the header row")
+    export.set_defaults(func=export_experiment)
+
     args = parser.parse_args()
     if getattr(args, "func", None) is None:
         parser.print_help()
@@ -408,9 +2480,53 @@ def parse_args():
     return args
 
 
+USER_ERRORS = (
+    InvalidFilter, InvalidRepository, InvalidArguments, ExperimentNotFound, AmbiguousExperiment,
+    MissingCheckpoint, TimeoutError,
+)
+
+
+def is_user_error(e):
+    # user errors have their own exception types, others are crashes that keep their traceback
+    return isinstance(e, USER_ERRORS)
+
+
+def error_message(e):
+    if isinstance(e, ExperimentNotFound):
+        return f"no experiment matching '{e}'"
+    return str(e)
+
+
 def main() -> None:
+    global verbose, na_string, local_dates, absolute_paths
+
     args = parse_args()
-    args.func(args)
+    verbose = args.verbose
+    na_string = args.na_string
+    local_dates = args.local or sake_setting("local", False)
+    absolute_paths = args.absolute_paths
+    if args.strip_ansi:
+        sys.stdout = AnsiStrippingWriter(sys.stdout)
+    Experiment.precedence = args.prefer
+    try:
+        args.func(args)
+        if args.fail_on_warning and len(emitted_warnings) > 0:
+            sys.exit(1)
+    except KeyboardInterrupt:
+        print("interrupted", file=sys.stderr)
+        # a normal exit would wait for the threads still blocked on a read or a command
+        sys.stdout.flush()
+        sys.stderr.flush()
+        os._exit(130)
+    except Exception as e:
+        if args.error_format == "json":
+            error = {"error": error_message(e), "kind": type(e).__name__}
+            print(json.dumps(error), file=sys.stderr)
+            sys.exit(1)
+        if not is_user_error(e):
+            raise
+        print(f"error: {error_message(e)}", file=sys.stderr)
+        sys.exit(1)
 
 
 if __name__ == "__main__":
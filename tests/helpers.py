@@ -0,0 +1,85 @@
+import json
+import os
+import shutil
+import subprocess
+import sys
+import tempfile
+import unittest
+from datetime import datetime, timedelta
+from pathlib import Path
+
+ROOT = Path(__file__).resolve().parent.parent
+SAKE = ROOT / "sake.py"
+sys.path.insert(0, str(ROOT))
+
+
+def timestamp(ago=timedelta()):
+    return (datetime.utcnow() - ago).strftime("%Y-%m-%dT%H:%M:%S.%fZ")
+
+
+def checkpoint(step, metrics, primary="loss", goal="minimize", path=".", created=None):
+    return {
+        "id": f"c{step:039d}",
+        "created": created or timestamp(timedelta(minutes=10 - step)),
+        "step": step,
+        "metrics": metrics,
+        "path": path,
+        "primary_metric": {"name": primary, "goal": goal},
+    }
+
+
+def full_id(prefix):
+    return prefix + "0" * (40 - len(prefix))
+
+
+class SakeTestCase(unittest.TestCase):
+    repository = "file://.keepsake"
+
+    def setUp(self):
+        self.dir = Path(tempfile.mkdtemp())
+        self.addCleanup(shutil.rmtree, self.dir, ignore_errors=True)
+        (self.dir / "keepsake.yml").write_text(f'repository: "{self.repository}"\n')
+        self.metadata = self.dir / ".keepsake" / "metadata" / "experiments"
+        self.metadata.mkdir(parents=True)
+
+    def add_experiment(self, prefix, params=None, checkpoints=None, created=None,
+                       command="train.py", **fields):
+        expe_id = full_id(prefix)
+        expe = {
+            "id": expe_id,
+            "created": created or timestamp(timedelta(hours=1)),
+            "params": params if params is not None else {},
+            "checkpoints": checkpoints,
+            "command": command,
+            "config": {"repository": self.repository, "storage": "file"},
+            "path": ".",
+        }
+        expe.update(fields)
+        self.write_experiment(expe)
+        return expe_id
+
+    def write_experiment(self, expe, name=None):
+        path = self.metadata / (name or f"{expe['id']}.json")
+        path.write_text(json.dumps(expe, indent=2))
+        return path
+
+    def sake(self, *args, check=True, input=None, env=None, cwd=None, timeout=30):
+        process = subprocess.run(
+            [sys.executable, str(SAKE), *args],
+            cwd=cwd or self.dir,
+            input=input,
+            capture_output=True,
+            text=True,
+            env={**os.environ, "COLUMNS": "200", **(env or {})},
+            timeout=timeout,
+        )
+        if check and process.returncode != 0:
+            self.fail(f"sake {' '.join(args)} exited with {process.returncode}:\n{process.stderr}")
+        return process
+
+    def ids(self, *args, env=None):
+        return [line[:7] for line in self.sake("list", "-q", *args, env=env).stdout.split()]
+
+    def rows(self, *args):
+        stdout = self.sake("list", "--format", "jsonl", *args).stdout
+        return [json.loads(line) for line in stdout.splitlines()]
@@ -0,0 +1,216 @@
+import csv
+import io
+import json
+import unittest
+from datetime import timedelta
+
+from helpers import SakeTestCase, checkpoint, full_id, timestamp
+import sake
+
+
+class CommandsTest(SakeTestCase):
+    def setUp(self):
+        super().setUp()
+        self.add_experiment(
+            "aaa1111",
+            params={"lr": 0.1, "model": {"depth": 50, "layers": [1, 2]}},
+            checkpoints=[checkpoint(1, {"loss": 2.0, "acc": 0.5}), checkpoint(2, {"loss": 0.5, "acc": 0.7})],
+            created=timestamp(timedelta(hours=2)),
+            python_version="3.8.5",
+            python_packages={"torch": "1.7.0", "numpy": "1.19.0"},
+        )
+        self.add_experiment(
+            "bbb2222",
+            params={"lr": 0.01, "model": {"depth": 50, "layers": [1, 2]}},
+            checkpoints=[checkpoint(1, {"loss": 1.0, "f1": 0.3})],
+            created=timestamp(timedelta(hours=1)),
+            python_version="3.9.1",
+            python_packages={"torch": "1.8.0", "numpy": "1.19.0"},
+        )
+        self.add_experiment("ccc3333", params={"lr": 0.001})
+
+    def test_show(self):
+        stdout = self.sake("show", "aaa").stdout
+        self.assertIn("Experiment aaa1111", stdout)
+        self.assertIn('"depth": 50', stdout)
+        self.assertIn("loss: 0.5", stdout)
+
+    def test_show_table(self):
+        stdout = self.sake("show", "aaa", "-t").stdout
+        self.assertIn("model.depth", stdout)
+        self.assertIn("model.layers.1", stdout)
+        self.assertIn("primary_name", stdout)
+
+    def test_show_checkpoint(self):
+        stdout = self.sake("show", "aaa", "--checkpoint", "1").stdout
+        self.assertIn("Experiment aaa1111 at step 1", stdout)
+        self.assertIn("loss: 2.0", stdout)
+        process = self.sake("--error-format", "json", "show", "aaa", "--checkpoint", "5", check=False)
+        self.assertIn("no checkpoint at step 5 (available steps: 1, 2)", process.stderr)
+
+    def test_show_raw_metrics(self):
+        stdout = self.sake("show", "aaa", "--raw-metrics").stdout
+        self.assertEqual(json.loads(stdout), {"loss": 0.5, "acc": 0.7})
+        stdout = self.sake("show", "aaa", "--raw-metrics", "--checkpoint", "1").stdout
+        self.assertEqual(json.loads(stdout), {"loss": 2.0, "acc": 0.5})
+
+    def test_tag(self):
+        self.sake("tag", "aaa", "best")
+        self.assertIn("Experiment aaa1111", self.sake("show", "best").stdout)
+        process = self.sake("--error-format", "json", "tag", "bbb", "best", check=False)
+        self.assertIn("tag 'best' is already used by experiment aaa1111", process.stderr)
+        self.assertIn("best", self.sake("list").stdout)
+
+    def test_rm_drops_tag(self):
+        self.sake("tag", "aaa", "best")
+        self.sake("rm", "aaa", "-y")
+        self.assertEqual(json.loads((self.dir / ".sake" / "tags.json").read_text()), {})
+        self.sake("tag", "bbb", "best")
+        self.assertIn("Experiment bbb2222", self.sake("show", "best").stdout)
+
+    def test_cat(self):
+        path = self.metadata / f"{full_id('aaa1111')}.json"
+        self.assertEqual(self.sake("cat", "aaa").stdout, path.read_text())
+
+    def test_grep(self):
+        self.assertEqual(self.sake("grep", "1.8.0").stdout, 'bbb2222: "torch": "1.8.0",\n')
+        self.assertEqual(self.sake("grep", "--regex", r'"lr": 0\.0+1').stdout.splitlines(),
+                         ['bbb2222: "lr": 0.01,', 'ccc3333: "lr": 0.001'])
+        process = self.sake("grep", "--regex", "(", check=False)
+        self.assertEqual(process.returncode, 1)
+        self.assertTrue(process.stderr.startswith("error: invalid regex '('"), process.stderr)
+
+    def test_diff(self):
+        stdout = self.sake("diff", "aaa", "bbb").stdout
+        for title in ("Params", "Config", "Metrics", "Environment"):
+            self.assertIn(title, stdout)
+        self.assertIn("0.01", stdout)
+        self.assertNotIn("numpy", stdout)
+        self.assertIn("numpy", self.sake("diff", "aaa", "bbb", "--all").stdout)
+
+    def test_diff_metrics(self):
+        stdout = self.sake("diff", "aaa", "bbb", "--metrics").stdout
+        self.assertIn("+0.5", stdout)
+        self.assertIn("aaa1111 (step 2)", stdout)
+        self.assertIn("f1", stdout)
+        # loss is minimized, aaa1111 wins with 0.5 against 1.0
+        line = next(line for line in stdout.splitlines() if line.lstrip("│ ").startswith("loss"))
+        self.assertRegex(line, r"\+0\.5.*aaa1111")
+
+    def test_diff_metrics_missing(self):
+        stdout = self.sake("--na-string", "N/A", "diff", "aaa", "bbb", "--metrics").stdout
+        line = next(line for line in stdout.splitlines() if line.lstrip("│ ").startswith("acc"))
+        self.assertEqual(line.count("N/A"), 3)
+
+    def test_diff_env(self):
+        stdout = self.sake("diff", "aaa", "bbb", "--env").stdout
+        self.assertIn("torch", stdout)
+        self.assertIn("1.8.0", stdout)
+        self.assertRegex(stdout, r"python.*3\.8\.5.*3\.9\.1")
+        self.assertNotIn("numpy", stdout)
+
+    def test_top(self):
+        stdout = self.sake("top", "loss").stdout
+        self.assertLess(stdout.index("aaa1111"), stdout.index("bbb2222"))
+        self.assertNotIn("ccc3333", stdout)
+        self.assertNotIn("bbb2222", self.sake("top", "loss", "-n", "1").stdout)
+
+    def test_history(self):
+        stdout = self.sake("history", "loss", "-g", "model").stdout
+        self.assertIn("Best loss by model", stdout)
+        self.assertIn("aaa1111", stdout)
+        self.assertNotIn("bbb2222", stdout)
+
+    def test_export(self):
+        rows = list(csv.reader(io.StringIO(self.sake("export", "aaa").stdout)))
+        self.assertEqual(rows[0], ["step", "created", "loss", "acc"])
+        self.assertEqual([row[0] for row in rows[1:]], ["1", "2"])
+
+    def test_export_no_header(self):
+        rows = list(csv.reader(io.StringIO(self.sake("export", "aaa").stdout)))
+        no_header = list(csv.reader(io.StringIO(self.sake("export", "aaa", "--no-header").stdout)))
+        self.assertEqual(no_header, rows[1:])
+
+    def test_export_differing_keys(self):
+        self.add_experiment("ddd4444", checkpoints=[checkpoint(1, {"loss": 1.0}), checkpoint(2, {"loss": 0.8, "f1": 0.4})])
+        rows = list(csv.reader(io.StringIO(self.sake("export", "ddd").stdout)))
+        self.assertEqual(rows[0], ["step", "created", "loss", "f1"])
+        self.assertEqual([row[2:] for row in rows[1:]], [["1.0", ""], ["0.8", "0.4"]])
+
+    def test_export_output_dir(self):
+        process = self.sake("export", "--output-dir", "all")
+        self.assertEqual(sorted(path.name for path in (self.dir / "all").iterdir()), ["aaa1111.csv", "bbb2222.csv"])
+        self.assertIn("acc", (self.dir / "all" / "aaa1111.csv").read_text())
+        process = self.sake("export", "--output-dir", "out", "-f", "lr<0.05")
+        self.assertIn("ccc3333 has no checkpoints, skipping", process.stderr)
+        self.assertEqual(sorted(path.name for path in (self.dir / "out").iterdir()), ["bbb2222.csv"])
+        self.assertIn("f1", (self.dir / "out" / "bbb2222.csv").read_text())
+
+    def test_rm_dry_run(self):
+        process = self.sake("rm", "aaa", "bbb", "--dry-run")
+        self.assertIn("2 experiment(s) will be deleted", process.stdout)
+        self.assertEqual(len(self.ids()), 3)
+
+    def test_rm_overlapping_ids(self):
+        process = self.sake("rm", "aaa", "a*", "--dry-run")
+        self.assertIn("1 experiment(s) will be deleted", process.stdout)
+        self.assertEqual(process.stdout.count("aaa1111"), 1)
+        self.sake("rm", "aaa", "a*", "-y")
+        self.assertEqual(self.ids(), ["bbb2222", "ccc3333"])
+
+    def test_rm(self):
+        self.sake("rm", "aaa", input="n\n")
+        self.assertEqual(len(self.ids()), 3)
+        self.sake("rm", "aaa", input="y\n")
+        self.assertEqual(self.ids(), ["bbb2222", "ccc3333"])
+        self.assertFalse((self.metadata / f"{full_id('aaa1111')}.json").exists())
+
+    def test_rm_glob(self):
+        process = self.sake("--error-format", "json", "rm", "*", check=False)
+        self.assertIn("matches 3 experiments, use --yes", process.stderr)
+        self.sake("rm", "[ab]*", "-y")
+        self.assertEqual(self.ids(), ["ccc3333"])
+
+    def test_reproduce(self):
+        stdout = self.sake("repr", "aaa", input="n\n").stdout
+        self.assertIn("python train.py", stdout)
+        self.assertIn("Aborting", stdout)
+
+
+class FormattingTest(unittest.TestCase):
+    def test_duration(self):
+        self.assertEqual(sake.format_duration(42), "42s")
+        self.assertEqual(sake.format_duration(125), "2m5s")
+        self.assertEqual(sake.format_duration(3 * 3600 + 60), "3h1m")
+        self.assertEqual(sake.format_duration(90000), "1d1h")
+
+    def test_size(self):
+        self.assertEqual(sake.format_size(512), "512 B")
+        self.assertEqual(sake.format_size(1536), "1.5 KiB")
+        self.assertEqual(sake.format_size(3 * 1024 ** 3), "3.0 GiB")
+
+    def test_parse_duration(self):
+        self.assertEqual(sake.parse_duration("1h30m"), timedelta(hours=1, minutes=30))
+        with self.assertRaises(ValueError):
+            sake.parse_duration("3 fortnights")
+
+    def test_pretty_params(self):
+        self.assertEqual(sake.Experiment._present_param("lr", 0.1, True), "lr: 0.1")
+        self.assertEqual(sake.Experiment._present_param("model", {"depth": 50}, True),
+                         'model:\n  {\n    "depth": 50\n  }')
+        self.assertEqual(sake.Experiment._present_param("model", {"depth": 50}, False), "model: {'depth': 50}")
+
+    def test_trend_arrow(self):
+        expe = sake.Experiment({
+            "id": full_id("a"), "created": timestamp(), "params": {}, "command": "train.py",
+            "checkpoints": [checkpoint(1, {"loss": 2.0, "acc": 0.5}), checkpoint(2, {"loss": 1.0, "acc": 0.5})],
+        })
+        last = expe.checkpoints[1]
+        self.assertEqual(expe.get_trend(last, "loss"), "down")
+        self.assertEqual(expe.get_trend_arrow(last, "loss"), " [green]↓[/green]")
+        self.assertEqual(expe.get_trend_arrow(last, "acc"), " →")
+        self.assertEqual(expe.get_trend_arrow(expe.checkpoints[0], "loss"), "")
+
+
+if __name__ == "__main__":
+    unittest.main()
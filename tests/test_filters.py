@@ -0,0 +1,199 @@
+import json
+import unittest
+from datetime import timedelta
+
+from helpers import SakeTestCase, checkpoint, timestamp
+
+
+class FilterTest(SakeTestCase):
+    def setUp(self):
+        super().setUp()
+        self.add_experiment(
+            "aaa1111",
+            params={"optimizer": "Adam", "lr": 0.1, "model": "resnet50", "tags": ["baseline", "v1"],
+                    "flags": [True], "seq": [1, 2]},
+            checkpoints=[checkpoint(1, {"loss": 2.0, "acc": 0.5}), checkpoint(2, {"loss": 0.5, "acc": 0.7})],
+            created=timestamp(timedelta(days=3)),
+            command="train.py --lr 0.1",
+        )
+        self.add_experiment(
+            "bbb2222",
+            params={"optimizer": "sgd", "lr": 0.01, "model": "vgg16", "tags": ["v2"], "lr_str": "10"},
+            checkpoints=[checkpoint(1, {"loss": 1.0, "acc": 0.6})],
+            created=timestamp(timedelta(minutes=30)),
+            command="eval.py",
+        )
+        self.add_experiment(
+            "ccc3333",
+            params={"optimizer": "sgd", "lr": "n/a"},
+            checkpoints=None,
+            created=timestamp(timedelta(minutes=10)),
+        )
+
+    def test_prefix_and_suffix(self):
+        self.assertEqual(self.ids("-f", "model^=res"), ["aaa1111"])
+        self.assertEqual(self.ids("-f", "model$=16"), ["bbb2222"])
+        self.assertEqual(self.ids("-f", "model^=vgg19"), [])
+
+    def test_relative_dates(self):
+        self.assertEqual(self.ids("-f", "created>now-1h"), ["bbb2222", "ccc3333"])
+        self.assertEqual(self.ids("-f", "created<now-1d"), ["aaa1111"])
+
+    def test_today(self):
+        self.assertNotIn("aaa1111", self.ids("-f", "created=today"))
+        self.add_experiment("ddd4444", created=timestamp())
+        self.assertIn("ddd4444", self.ids("-f", "created=today"))
+
+    def test_checkpoint_count(self):
+        self.assertEqual(self.ids("-f", "checkpoints>0"), ["aaa1111", "bbb2222"])
+        self.assertEqual(self.ids("-f", "checkpoints=2"), ["aaa1111"])
+
+    def test_min_checkpoints(self):
+        self.assertEqual(self.ids("--min-checkpoints", "2"), ["aaa1111"])
+        self.assertEqual(self.ids("--min-checkpoints", "1"), ["aaa1111", "bbb2222"])
+
+    def test_checkpoint_exists(self):
+        (self.dir / ".keepsake" / "model.pt").write_text("weights")
+        self.add_experiment("ddd4444", checkpoints=[checkpoint(1, {"loss": 1.0}, path="model.pt")])
+        self.add_experiment("eee5555", checkpoints=[checkpoint(1, {"loss": 1.0}, path="missing.pt")])
+        self.assertEqual(self.ids("-f", "checkpoint-exists"), ["aaa1111", "ddd4444", "bbb2222"])
+
+    def test_path_exists(self):
+        self.add_experiment("ddd4444", path="missing")
+        self.assertNotIn("ddd4444", self.ids("-f", "path-exists"))
+        self.assertIn("aaa1111", self.ids("-f", "path-exists"))
+
+    def test_step(self):
+        self.assertEqual(self.ids("-f", "step=2"), ["aaa1111"])
+        # a param named step hides the built-in, for filters and sorting alike
+        self.add_experiment("ddd4444", params={"step": 100}, checkpoints=[checkpoint(1, {"loss": 1.0})])
+        self.assertEqual(self.ids("-f", "step=100"), ["ddd4444"])
+        self.assertEqual(self.ids("-f", "step=1"), ["bbb2222"])
+        self.assertEqual(self.ids("--sort", "step"), ["bbb2222", "aaa1111", "ddd4444", "ccc3333"])
+
+    def test_ignore_case(self):
+        self.assertEqual(self.ids("-f", "optimizer=adam"), [])
+        self.assertEqual(self.ids("-f", "optimizer=adam", "--ignore-case"), ["aaa1111"])
+
+    def test_precedence(self):
+        self.add_experiment("ddd4444", params={"loss": "param"}, checkpoints=[checkpoint(1, {"loss": 3.0})])
+        self.assertEqual(self.ids("-f", "loss=param"), ["ddd4444"])
+        self.assertEqual(self.sake("--prefer", "metrics", "list", "-q", "-f", "loss=3.0").stdout[:7], "ddd4444")
+        self.assertEqual(self.ids("-f", "metrics.loss=3.0"), ["ddd4444"])
+        self.assertEqual(self.sake("--prefer", "metrics", "list", "-q", "-f", "params.loss=param").stdout[:7], "ddd4444")
+
+    def test_config_field(self):
+        self.assertEqual(len(self.ids("-f", "config.storage=file")), 3)
+        self.assertEqual(self.ids("-f", "config.storage=s3"), [])
+
+    def test_contains(self):
+        self.assertEqual(self.ids("-f", "tags contains v2"), ["bbb2222"])
+        self.assertEqual(self.ids("-f", "seq contains 2"), ["aaa1111"])
+        self.assertEqual(self.ids("-f", "optimizer contains sgd"), [])
+
+    def test_contains_bool(self):
+        self.assertEqual(self.ids("-f", "flags contains true"), ["aaa1111"])
+        self.assertEqual(self.ids("-f", "flags contains false"), [])
+        self.assertEqual(self.ids("-f", "flags contains 1"), [])
+        self.assertEqual(self.ids("-f", "seq contains 1.0"), ["aaa1111"])
+        self.add_experiment("ddd4444", params={"tags": ["1", "false"]})
+        self.assertEqual(self.ids("-f", "tags contains false"), ["ddd4444"])
+        self.assertEqual(self.ids("-f", "tags contains '1'"), ["ddd4444"])
+
+    def test_in(self):
+        self.assertEqual(self.ids("-f", "res in model"), ["aaa1111"])
+
+    def test_coercion(self):
+        self.assertEqual(self.ids("-f", "lr_str>9"), [])
+        self.assertEqual(self.ids("--as", "lr_str:number", "-f", "lr_str>9"), ["bbb2222"])
+
+    def test_require_numeric(self):
+        process = self.sake("list", "-q", "-f", "lr>0.05", "--require-numeric")
+        self.assertEqual(process.stdout.split(), ["aaa1111" + "0" * 33])
+        self.assertIn("ccc3333 has no numeric value for 'lr'", process.stderr)
+
+    def test_len_and_count(self):
+        self.assertEqual(self.ids("-f", "len(tags)=2"), ["aaa1111"])
+        self.assertEqual(self.ids("-f", "count(checkpoints)=0"), ["ccc3333"])
+        self.assertEqual(self.ids("-f", "len(command)<8"), ["bbb2222"])
+
+    def test_boolean_expressions(self):
+        self.assertEqual(self.ids("-f", "(optimizer=Adam or optimizer=sgd) and not lr=0.01"),
+                         ["aaa1111", "ccc3333"])
+        self.assertEqual(self.ids("-f", "optimizer=sgd AND model=vgg16"), ["bbb2222"])
+
+    def test_quoted_values(self):
+        self.add_experiment("ddd4444", params={"note": "a and b"})
+        self.assertEqual(self.ids("-f", "note='a and b'"), ["ddd4444"])
+        self.assertEqual(self.ids("-f", 'note="a and b"'), ["ddd4444"])
+
+    def test_primary(self):
+        self.assertEqual(self.ids("-f", "primary<0.8"), ["aaa1111"])
+        self.assertEqual(self.ids("-f", "primary_name=loss"), ["aaa1111", "bbb2222"])
+
+    def test_between(self):
+        self.assertEqual(self.ids("-f", "lr between 0.01..0.1"), ["aaa1111", "bbb2222"])
+        self.assertEqual(self.ids("-f", "lr between 0.01<..0.1"), ["aaa1111"])
+        self.assertEqual(self.ids("-f", "lr between 0.01..<0.1"), ["bbb2222"])
+        self.add_experiment("ddd4444", params={"lr": 0.05})
+        self.assertEqual(self.ids("-f", "lr between 0.01<..<0.1"), ["ddd4444"])
+
+    def test_between_dates(self):
+        self.assertEqual(self.ids("-f", "created between 2020-01-01..2030-01-01"), ["aaa1111", "bbb2222", "ccc3333"])
+        self.assertEqual(self.ids("-f", "created between now-1d<..<now"), ["bbb2222", "ccc3333"])
+        self.assertEqual(self.ids("-f", "created between 2020-01-01<..<now-1d"), ["aaa1111"])
+
+    def test_improvement(self):
+        # loss is minimized and goes from 2.0 to 0.5
+        self.assertEqual(self.ids("-f", "improvement(loss)>0.5"), ["aaa1111"])
+        self.assertEqual(self.ids("-f", "improvement(loss)=1.5"), ["aaa1111"])
+        self.assertEqual(self.ids("-f", "improvement(loss)<0"), [])
+        self.add_experiment("ddd4444", checkpoints=[
+            checkpoint(1, {"acc": 0.9}, primary="acc", goal="maximize"),
+            checkpoint(2, {"acc": 0.7}, primary="acc", goal="maximize"),
+        ])
+        self.assertEqual(self.ids("-f", "improvement(acc)<0"), ["ddd4444"])
+
+    def test_improvement_without_goal(self):
+        # acc is not the primary metric of aaa1111, whether it improved is unknown
+        self.assertEqual(self.ids("-f", "improvement(acc)>0"), [])
+        self.assertEqual(self.ids("-f", "improvement(acc)<0"), [])
+
+    def test_explain(self):
+        process = self.sake("list", "-q", "-f", "optimizer=sgd", "--explain")
+        self.assertIn("excluded aaa1111: optimizer=sgd (had Adam)", process.stderr)
+
+    def test_explain_runs_filters_once(self):
+        process = self.sake("--fail-on-warning", "list", "-q", "-f", "lr>0.05", "-f", "model^=res",
+                            "--require-numeric", "--explain", check=False)
+        self.assertEqual(process.stderr.count("ccc3333 has no numeric value for 'lr'"), 1)
+        self.assertIn("excluded ccc3333: lr>0.05 (had n/a)", process.stderr)
+        self.assertIn("excluded bbb2222: lr>0.05 (had 0.01)", process.stderr)
+        self.assertNotIn("model^=res", process.stderr)
+
+    def test_verbose_diagnostics_on_stderr(self):
+        process = self.sake("-v", "list", "-q", "-f", "optimizer=sgd")
+        self.assertIn("filter: optimizer=sgd", process.stderr)
+        self.assertIn("2 experiments left after filtering", process.stderr)
+        self.assertEqual(len(process.stdout.split()), 2)
+
+    def test_filter_cmd(self):
+        self.assertEqual(self.ids("--filter-cmd", "grep -q resnet50"), ["aaa1111"])
+
+    def test_invalid_filters(self):
+        for raw_filter, message in [
+            ("lr", "expected operator"),
+            ("=0.1", "empty field name before '='"),
+            ("lr>", "empty value after '>'"),
+            ("(lr=0.1", "unclosed '('"),
+            ("size(tags)=1", "unknown function 'size'"),
+        ]:
+            process = self.sake("--error-format", "json", "list", "-f", raw_filter, check=False)
+            self.assertEqual(process.returncode, 1)
+            error = json.loads(process.stderr)
+            self.assertEqual(error["kind"], "InvalidFilter")
+            self.assertIn(message, error["error"])
+
+
+if __name__ == "__main__":
+    unittest.main()
@@ -0,0 +1,496 @@
+import csv
+import io
+import json
+import os
+import signal
+import subprocess
+import sys
+import time
+import unittest
+from datetime import datetime, timedelta, timezone
+from unittest import mock
+
+import yaml
+
+from helpers import SAKE, SakeTestCase, checkpoint, full_id, timestamp
+import sake
+
+
+class ListTest(SakeTestCase):
+    def setUp(self):
+        super().setUp()
+        self.add_experiment(
+            "aaa1111",
+            params={"lr": 0.1, "batch_size": 32, "model": {"depth": 50}},
+            checkpoints=[checkpoint(1, {"loss": 2.0, "acc": 0.5}), checkpoint(2, {"loss": 0.5, "acc": 0.7})],
+            created="2026-01-01T10:00:00.000000Z",
+            command="python -u train.py --lr 0.1",
+        )
+        self.add_experiment(
+            "bbb2222",
+            params={"lr": 0.01, "batch_size": 32, "dropout": 0.5},
+            checkpoints=[checkpoint(1, {"loss": 1.0, "acc": 0.6},
+                                    created=timestamp(timedelta(days=2)))],
+            created=timestamp(timedelta(days=3)),
+            command="train.py --lr 0.01",
+        )
+        self.add_experiment("ccc3333", params={"lr": 0.01, "batch_size": 32}, created=timestamp(timedelta(hours=1)))
+
+    def test_quiet(self):
+        self.assertEqual(self.ids(), ["aaa1111", "bbb2222", "ccc3333"])
+
+    def test_json_rows(self):
+        rows = json.loads(self.sake("list", "--format", "json").stdout)
+        self.assertEqual([row["id"] for row in rows], [full_id(p) for p in ("aaa1111", "bbb2222", "ccc3333")])
+        self.assertEqual(rows[0]["params"]["lr"], 0.1)
+        self.assertEqual(rows[0]["step"], 2)
+        self.assertEqual(rows[0]["metrics"], {"loss": 0.5, "acc": 0.7})
+        self.assertIsNone(rows[2]["step"])
+
+    def test_compact_json(self):
+        stdout = self.sake("list", "--format", "json", "--compact").stdout
+        self.assertEqual(stdout.count("\n"), 1)
+        self.assertEqual(len(json.loads(stdout)), 3)
+
+    def test_yaml(self):
+        rows = json.loads(self.sake("list", "--format", "json").stdout)
+        self.assertEqual(yaml.safe_load(self.sake("list", "--format", "yaml").stdout), rows)
+
+    def test_csv(self):
+        stdout = self.sake("list", "--format", "csv").stdout
+        header, *lines = list(csv.reader(io.StringIO(stdout)))
+        self.assertEqual(header[:2], ["id", "created"])
+        self.assertIn("dropout", header)
+        self.assertEqual(len(lines), 3)
+        self.assertEqual(lines[2][header.index("step")], "")
+
+    def test_na_string(self):
+        stdout = self.sake("--na-string", "NA", "list", "--format", "csv").stdout
+        header, *lines = list(csv.reader(io.StringIO(stdout)))
+        self.assertEqual(lines[2][header.index("step")], "NA")
+        self.assertEqual(lines[0][header.index("dropout")], "NA")
+
+    def test_na_string_table(self):
+        stdout = self.sake("--na-string", "N/A", "list", "--at-step", "2").stdout
+        self.assertIn("N/A", self.cell_line(stdout, "bbb2222"))
+        self.assertNotIn("N/A", self.cell_line(stdout, "aaa1111"))
+        self.sake("tag", "aaa", "best")
+        stdout = self.sake("--na-string", "N/A", "list").stdout
+        self.assertIn("N/A", self.cell_line(stdout, "ccc3333"))
+        self.assertNotIn("N/A", self.cell_line(stdout, "aaa1111"))
+
+    def test_csv_no_header(self):
+        stdout = self.sake("list", "--format", "csv", "--no-header").stdout
+        first = next(csv.reader(io.StringIO(stdout)))
+        self.assertEqual(first[0], full_id("aaa1111"))
+
+    def test_flatten(self):
+        row = self.rows("--flatten")[0]
+        self.assertEqual(row["params.model.depth"], 50)
+        self.assertEqual(row["metrics.loss"], 0.5)
+        self.assertNotIn("params", row)
+
+    def test_all_fields(self):
+        rows = json.loads(self.sake("list", "--format", "json", "--all-fields").stdout)
+        self.assertEqual(set(row["params"]["dropout"] is None for row in rows), {True, False})
+        self.assertEqual([row["state"] for row in rows], ["active", "stale", "empty"])
+        self.assertEqual(rows[0]["script"], "train.py")
+
+    def test_select_params(self):
+        rows = self.rows("-s", "lr")
+        self.assertEqual(rows[0]["params"], {"lr": 0.1})
+
+    def test_select_missing_fields(self):
+        rows = self.rows("-s", "dropout", "-s", "acc", "-s", "state")
+        self.assertEqual([row["params"] for row in rows], [{"dropout": None}, {"dropout": 0.5}, {"dropout": None}])
+        self.assertEqual([row["metrics"] for row in rows], [{"acc": 0.7}, {"acc": 0.6}, {"acc": None}])
+        self.assertEqual([row["state"] for row in rows], ["active", "stale", "empty"])
+        rows = self.rows("-s", "optimizer")
+        self.assertEqual(rows[0]["params"], {"optimizer": None})
+
+    def test_null_as_missing(self):
+        rows = self.rows("-s", "dropout", "-s", "acc", "--null-as-missing")
+        self.assertEqual([row["params"] for row in rows], [{}, {"dropout": 0.5}, {}])
+        self.assertEqual(rows[2]["metrics"], {})
+        self.assertNotIn("step", rows[2])
+        self.add_experiment("ddd4444", params={"seed": None})
+        rows = json.loads(self.sake("list", "--format", "json", "--all-fields", "--null-as-missing").stdout)
+        self.assertEqual(rows[3]["params"], {"seed": None})
+        self.assertIsNone(rows[3]["primary"])
+        self.assertIsNone(rows[2]["duration"])
+
+    def test_null_as_missing_step(self):
+        self.assertIsNone(self.rows()[2]["step"])
+        self.assertNotIn("step", self.rows("--null-as-missing")[2])
+
+    def test_columns_auto(self):
+        rows = self.rows("--columns", "auto")
+        self.assertEqual(list(rows[1]["params"]), ["lr", "model", "dropout"])
+        self.assertEqual(rows[0]["metrics"], {"loss": 0.5})
+
+    def test_view_file(self):
+        (self.dir / "view.txt").write_text("# fields\nlr\n\nloss\n")
+        rows = self.rows("--view", "view.txt")
+        self.assertEqual(rows[0]["params"], {"lr": 0.1})
+        self.assertEqual(rows[0]["metrics"], {"loss": 0.5})
+
+    def test_view_from_sake_toml(self):
+        (self.dir / ".sake.toml").write_text('[views]\nsmall = ["lr", "batch_size", "acc"]\n')
+        rows = self.rows("--view", "small")
+        self.assertEqual(rows[0]["params"], {"lr": 0.1, "batch_size": 32})
+        self.assertEqual(rows[0]["metrics"], {"acc": 0.7})
+
+    def test_template(self):
+        stdout = self.sake("list", "--template", "{id}|{lr}|{script}|{missing}|{{x}}").stdout
+        lines = stdout.splitlines()
+        self.assertEqual(lines[0], f"{full_id('aaa1111')}|0.1|train.py||{{x}}")
+        self.assertEqual(lines[1].split("|")[2], "train.py")
+
+    def test_out_template_file(self):
+        (self.dir / "row.txt").write_text("{lr}\n")
+        self.assertEqual(self.sake("list", "--out-template-file", "row.txt").stdout, "0.1\n0.01\n0.01\n")
+        (self.dir / "row.txt").write_text("{id}\n  lr={lr}\n")
+        stdout = self.sake("list", "--out-template-file", "row.txt", "-f", "lr=0.1").stdout
+        self.assertEqual(stdout, f"{full_id('aaa1111')}\n  lr=0.1\n")
+
+    def test_state(self):
+        self.add_experiment("ddd4444", checkpoints=[checkpoint(1, {"loss": 1.0}, created=timestamp())])
+        self.assertEqual(self.ids("-f", "state=active"), ["aaa1111", "ddd4444"])
+        self.assertEqual(self.ids("-f", "state=stale"), ["bbb2222"])
+        self.assertEqual(self.ids("-f", "state=empty"), ["ccc3333"])
+        self.assertEqual(self.ids("-f", "state=active", "--stale-after", "5m"), ["ddd4444"])
+
+    def test_state_template(self):
+        stdout = self.sake("list", "--template", "{state}").stdout
+        self.assertEqual(stdout.split(), ["active", "stale", "empty"])
+
+    def test_script(self):
+        self.add_experiment("ddd4444", command="python eval.py --split test")
+        self.assertEqual(self.ids("-f", "script=eval.py"), ["ddd4444"])
+        self.assertEqual(self.ids("-f", "script=train.py"), ["aaa1111", "bbb2222", "ccc3333"])
+
+    def test_primary_fields(self):
+        stdout = self.sake("list", "--template", "{primary_name}={primary}").stdout
+        self.assertEqual(stdout.splitlines(), ["loss=0.5", "loss=1.0", "="])
+
+    def test_at_step(self):
+        self.assertEqual(self.ids("--at-step", "1", "-f", "loss=2.0"), ["aaa1111"])
+        self.assertEqual(self.rows("--at-step", "1")[0]["metrics"], {"loss": 2.0, "acc": 0.5})
+
+    def test_metric_agg(self):
+        self.assertEqual(self.rows("--metric-agg", "mean")[0]["metrics"]["loss"], 1.25)
+        self.assertEqual(self.ids("--metric-agg", "max", "-f", "loss=2.0"), ["aaa1111"])
+
+    def test_each_metric_agg(self):
+        self.add_experiment("ddd4444", checkpoints=[
+            checkpoint(1, {"loss": 3.0, "acc": 0.9}), checkpoint(2, {"loss": 1.0, "acc": 0.6}),
+            checkpoint(3, {"loss": 2.0, "acc": 0.75}),
+        ])
+        for agg, expected in [
+            (None, {"loss": 1.0, "acc": 0.6}),
+            ("first", {"loss": 3.0, "acc": 0.9}),
+            ("last", {"loss": 2.0, "acc": 0.75}),
+            ("mean", {"loss": 2.0, "acc": 0.75}),
+            ("max", {"loss": 3.0, "acc": 0.9}),
+            ("min", {"loss": 1.0, "acc": 0.6}),
+            # the primary loss is minimized, acc has no goal and falls back to max
+            ("best", {"loss": 1.0, "acc": 0.9}),
+        ]:
+            args = ["--id-glob", "ddd*"] + ([] if agg is None else ["--metric-agg", agg])
+            self.assertEqual(self.rows(*args)[0]["metrics"], expected, agg)
+
+    def test_since_experiment(self):
+        self.assertEqual(self.ids("--since-experiment", "bbb"), ["ccc3333"])
+
+    def test_id_glob(self):
+        self.assertEqual(self.ids("--id-glob", "?bb*"), ["bbb2222"])
+
+    def test_file_newer_than(self):
+        old = time.time() - 3 * 86400
+        os.utime(self.metadata / f"{full_id('aaa1111')}.json", (old, old))
+        self.assertEqual(self.ids("--file-newer-than", "1d"), ["bbb2222", "ccc3333"])
+
+    def test_file_newer_than_aware_date(self):
+        # an hour before the cutoff, whatever the local time zone
+        old = datetime(2025, 12, 31, 23, tzinfo=timezone.utc).timestamp()
+        os.utime(self.metadata / f"{full_id('aaa1111')}.json", (old, old))
+        for tz in ("UTC", "Asia/Tokyo", "America/New_York"):
+            self.assertEqual(self.ids("--file-newer-than", "2026-01-01T00:00:00Z", env={"TZ": tz}),
+                             ["bbb2222", "ccc3333"])
+
+    def test_updated(self):
+        for i, prefix in enumerate(["bbb2222", "aaa1111", "ccc3333"]):
+            mtime = time.time() - 100 * (i + 1)
+            os.utime(self.metadata / f"{full_id(prefix)}.json", (mtime, mtime))
+        self.assertEqual(self.ids("--updated"), ["bbb2222", "aaa1111", "ccc3333"])
+
+    def test_sort_ties_by_id(self):
+        self.assertEqual(self.ids("--sort", "lr"), ["bbb2222", "ccc3333", "aaa1111"])
+        self.assertEqual(self.ids("--sort", "batch_size"), ["aaa1111", "bbb2222", "ccc3333"])
+
+    def test_sort_missing_values_last(self):
+        self.assertEqual(self.ids("--sort", "primary"), ["aaa1111", "bbb2222", "ccc3333"])
+        self.assertEqual(self.ids("--sort", "duration"), ["bbb2222", "aaa1111", "ccc3333"])
+        self.assertEqual(self.ids("--sort", "dropout"), ["bbb2222", "aaa1111", "ccc3333"])
+
+    def test_random_seed(self):
+        self.assertEqual(self.ids("--sort", "random", "--seed", "3"), self.ids("--sort", "random", "--seed", "3"))
+
+    def test_sample(self):
+        sample = self.ids("--sample", "2", "--seed", "1")
+        self.assertEqual(len(sample), 2)
+        self.assertEqual(sample, self.ids("--sample", "2", "--seed", "1"))
+        self.assertEqual(len(self.ids("--sample", "10")), 3)
+
+    def test_dedupe_by_command(self):
+        self.add_experiment("ddd4444", command="train.py --lr 0.01", created=timestamp(timedelta(days=1)))
+        self.assertEqual(self.ids("--dedupe-by-command"), ["aaa1111", "ddd4444", "ccc3333"])
+
+    def test_duplicate_ids(self):
+        expe = json.loads((self.metadata / f"{full_id('aaa1111')}.json").read_text())
+        expe["params"]["lr"] = 0.5
+        copy = self.write_experiment(expe, name="copy.json")
+        future = time.time() + 100
+        os.utime(copy, (future, future))
+        process = self.sake("list", "--template", "{lr}")
+        self.assertIn("experiment aaa1111 is defined in several files", process.stderr)
+        self.assertEqual(process.stdout.split()[0], "0.1")
+        latest = self.sake("list", "--template", "{lr}", "--duplicates", "latest")
+        self.assertEqual(latest.stdout.split()[0], "0.5")
+
+    def test_fail_on_warning(self):
+        self.write_experiment({"id": "broken"}, name="broken.json")
+        self.assertEqual(len(self.ids()), 3)
+        process = self.sake("--fail-on-warning", "list", "-q", check=False)
+        self.assertEqual(process.returncode, 1)
+        self.assertIn("skipping malformed experiment file", process.stderr)
+
+    def test_parallelism(self):
+        self.assertEqual(self.ids("--parallelism", "1"), ["aaa1111", "bbb2222", "ccc3333"])
+        process = self.sake("list", "-q", env={"SAKE_THREADS": "2"})
+        self.assertEqual(len(process.stdout.split()), 3)
+
+    def test_invalid_parallelism(self):
+        for value in ("0", "-1", "two"):
+            process = self.sake("list", "-q", "--parallelism", value, check=False)
+            self.assertEqual(process.returncode, 2)
+            self.assertIn("argument --parallelism: expected a number of threads of at least 1", process.stderr)
+            process = self.sake("list", "-q", check=False, env={"SAKE_THREADS": value})
+            self.assertEqual(process.returncode, 2)
+            self.assertNotIn("Traceback", process.stderr)
+        # only list reads SAKE_THREADS
+        self.assertIn("aaa1111", self.sake("show", "aaa", env={"SAKE_THREADS": "0"}).stdout)
+        self.assertEqual(len(self.ids(env={"SAKE_THREADS": "auto"})), 3)
+
+    def test_checkpoints_only(self):
+        rows = self.rows("--checkpoints-only")
+        self.assertEqual([(row["id"][:7], row["step"]) for row in rows],
+                         [("aaa1111", 1), ("aaa1111", 2), ("bbb2222", 1)])
+        rows = self.rows("--checkpoints-only", "--sort", "step")
+        self.assertEqual([row["step"] for row in rows], [1, 1, 2])
+
+    def test_absolute_paths(self):
+        row = json.loads(self.sake("--absolute-paths", "list", "--checkpoints-only", "--format", "jsonl")
+                         .stdout.splitlines()[0])
+        self.assertEqual(row["path"], str((self.dir / ".keepsake").resolve()))
+        self.assertEqual(self.rows("--checkpoints-only")[0]["path"], ".")
+
+    def test_since_last(self):
+        self.assertEqual(len(self.ids("--since-last")), 3)
+        self.assertEqual(self.ids("--since-last"), [])
+        self.add_experiment("ddd4444")
+        self.assertEqual(self.ids("--since-last"), ["ddd4444"])
+        self.assertEqual(len(self.ids("--since-last", "--reset-seen")), 4)
+
+    def test_deleted(self):
+        self.sake("rm", "ccc", "-y", "--reason", "diverged")
+        self.assertEqual(self.ids(), ["aaa1111", "bbb2222"])
+        tombstones = self.rows("--deleted")
+        self.assertEqual([(row["id"], row["reason"]) for row in tombstones], [(full_id("ccc3333"), "diverged")])
+        self.add_experiment("ccc3333")
+        self.assertEqual(self.ids(), ["aaa1111", "bbb2222"])
+
+    def test_table(self):
+        stdout = self.sake("list").stdout
+        self.assertIn("aaa1111", stdout)
+        self.assertIn("lr: 0.1", stdout)
+        self.assertIn("step 2 (best)", stdout)
+        self.assertIn("0 checkpoints", stdout)
+
+    def test_plain_listing(self):
+        process = self.sake("list")
+        self.assertEqual(process.stderr, "")
+        self.assertIn("aaa1111", process.stdout)
+        self.assertNotIn("filter", process.stdout)
+
+    def test_ls_alias(self):
+        self.assertEqual(self.sake("ls", "-q").stdout, self.sake("list", "-q").stdout)
+        self.assertEqual(self.sake("ls").stdout, self.sake("list").stdout)
+
+    def test_timeout(self):
+        self.assertEqual(self.ids("--timeout", "10s"), ["aaa1111", "bbb2222", "ccc3333"])
+        process = self.sake("list", "--timeout", "soon", check=False)
+        self.assertEqual(process.returncode, 2)
+
+    def cell_line(self, stdout, prefix):
+        return next(line for line in stdout.splitlines() if prefix in line)
+
+    def test_relative_time_column(self):
+        stdout = self.sake("list", "--relative-time").stdout
+        self.assertIn("1 hour ago", self.cell_line(stdout, "ccc3333"))
+        self.assertIn("3 days ago", self.cell_line(stdout, "bbb2222"))
+
+    def test_checkpoints_count(self):
+        stdout = self.sake("list", "--with-checkpoints-count").stdout
+        self.assertRegex(stdout, r"\s#(\s|$)")
+        for prefix, count in [("aaa1111", 2), ("bbb2222", 1), ("ccc3333", 0)]:
+            self.assertRegex(self.cell_line(stdout, prefix), rf"[|│]\s*{count}\s*[|│]?\s*$")
+
+    def test_align(self):
+        stdout = self.sake("list", "--align", "id:left", "--align", "params:right").stdout
+        self.assertIn("aaa1111", stdout)
+        process = self.sake("list", "--align", "id:middle", check=False)
+        self.assertEqual(process.returncode, 2)
+
+    def test_builtin_columns(self):
+        stdout = self.sake("list", "-s", "primary_name", "-s", "primary", "-s", "state", "-s", "duration").stdout
+        self.assertNotIn("Parameters", stdout)
+        self.assertNotIn("batch_size", stdout)
+        # created 3 days ago, checkpoint 2 days ago
+        self.assertRegex(self.cell_line(stdout, "bbb2222"), r"loss.*1\.0.*stale.*(1d0h|23h59m)")
+        stdout = self.sake("list", "-s", "lr", "-s", "script", "-s", "checkpoints").stdout
+        self.assertRegex(self.cell_line(stdout, "aaa1111"), r"lr: 0\.1.*train\.py.*2")
+        self.assertNotIn("batch_size", stdout)
+
+    def test_metric_delta_column(self):
+        stdout = self.sake("list", "--metric-delta", "loss", "--baseline", "bbb").stdout
+        self.assertIn("Δ loss", stdout)
+        self.assertIn("-0.5", self.cell_line(stdout, "aaa1111"))
+
+    def test_spark_column(self):
+        stdout = self.sake("list", "--spark", "loss").stdout
+        self.assertIn("█▁", self.cell_line(stdout, "aaa1111"))
+
+    def test_missing_delta(self):
+        stdout = self.sake("--na-string", "N/A", "list", "--metric-delta", "loss", "--baseline", "bbb").stdout
+        line = next(line for line in stdout.splitlines() if "ccc3333" in line)
+        self.assertIn("N/A", line)
+
+    def test_missing_sparkline(self):
+        stdout = self.sake("--na-string", "N/A", "list", "--spark", "loss").stdout
+        line = next(line for line in stdout.splitlines() if "ccc3333" in line)
+        self.assertIn("N/A", line)
+
+    def test_local_dates(self):
+        stdout = self.sake("--local", "list", "-f", "lr=0.1", env={"TZ": "Asia/Tokyo"}).stdout
+        self.assertIn("19:00", stdout)
+        self.assertIn("10:00", self.sake("list", "-f", "lr=0.1", env={"TZ": "Asia/Tokyo"}).stdout)
+
+
+class InterruptTest(SakeTestCase):
+    def interrupt(self, *args, started=None):
+        # like ctrl-c in a terminal, the whole process group gets the signal
+        stderr_path = self.dir / "stderr.txt"
+        with open(stderr_path, "w") as stderr:
+            process = subprocess.Popen([sys.executable, str(SAKE), *args], cwd=self.dir,
+                                       stdout=subprocess.DEVNULL, stderr=stderr, start_new_session=True)
+        self.addCleanup(lambda: process.poll() is None and process.kill())
+        deadline = time.monotonic() + 10
+        while started is not None and not (self.dir / started).exists() and time.monotonic() < deadline:
+            time.sleep(0.1)
+        time.sleep(1)
+        os.killpg(process.pid, signal.SIGINT)
+        try:
+            process.wait(timeout=5)
+        except subprocess.TimeoutExpired:
+            self.fail("sake did not exit after SIGINT")
+        self.assertEqual(process.returncode, 130)
+        self.assertIn("interrupted", stderr_path.read_text())
+
+    def test_interrupted_filter_command(self):
+        self.add_experiment("aaa1111")
+        self.interrupt("list", "-q", "--filter-cmd", "touch started; sleep 30", started="started")
+
+    def test_blocked_read(self):
+        os.mkfifo(self.metadata / "zzz.json")
+        self.interrupt("list", "-q")
+
+    def test_filter_command_ignoring_sigint(self):
+        self.add_experiment("aaa1111")
+        self.interrupt("list", "-q", "--filter-cmd", "trap '' INT; touch started; sleep 30", started="started")
+
+
+class ListHelpersTest(unittest.TestCase):
+    def experiment(self, **fields):
+        expe = {"id": full_id("aaa"), "created": timestamp(), "params": {}, "checkpoints": None,
+                "command": "train.py"}
+        expe.update(fields)
+        return sake.Experiment(expe)
+
+    def test_heatmap(self):
+        self.assertEqual(sake.heatmap_style(1.0, 0.0, 1.0, "maximize"), "green")
+        self.assertEqual(sake.heatmap_style(1.0, 0.0, 1.0, "minimize"), "red")
+        self.assertIsNone(sake.heatmap_style("x", 0.0, 1.0, "maximize"))
+        experiments = [
+            self.experiment(id=full_id("a"), checkpoints=[checkpoint(1, {"loss": 1.0})]),
+            self.experiment(id=full_id("b"), checkpoints=[checkpoint(1, {"loss": 2.0})]),
+        ]
+        heatmap = sake.compute_heatmap(experiments, "loss")
+        self.assertEqual(heatmap[full_id("a")], {"loss": "green"})
+        self.assertEqual(heatmap[full_id("b")], {"loss": "red"})
+
+    def test_deltas(self):
+        baseline = self.experiment(id=full_id("a"), checkpoints=[checkpoint(1, {"loss": 1.0})])
+        better = self.experiment(id=full_id("b"), checkpoints=[checkpoint(1, {"loss": 0.5})])
+        deltas = sake.compute_deltas([baseline, better], "loss", baseline)
+        self.assertEqual(deltas[full_id("b")], "[green]-0.5[/green]")
+        self.assertEqual(deltas[full_id("a")], "[default]+0[/default]")
+
+    def test_sparkline(self):
+        self.assertEqual(sake.sparkline([1, 2, 3]), "▁▅█")
+        self.assertEqual(sake.sparkline([2, 2]), "▁▁")
+        self.assertEqual(sake.sparkline([]), "")
+
+    def test_relative_time(self):
+        now = sake.datetime.utcnow()
+        self.assertEqual(sake.format_relative_time(now - timedelta(hours=2, minutes=5)), "2 hours ago")
+        self.assertEqual(sake.format_relative_time(now - timedelta(days=1, hours=1)), "1 day ago")
+        self.assertEqual(sake.format_relative_time(now + timedelta(hours=1)), "in the future")
+
+    def test_alignment(self):
+        self.assertEqual(sake.parse_alignment("Params:right"), ("Params", "right"))
+        with self.assertRaises(Exception):
+            sake.parse_alignment("params:middle")
+
+    def test_column_justify(self):
+        columns = [("id", "center"), ("#", "left"), ("state", "left")]
+        rows = [["1234567", "2", "active"], ["12e4567", "", "stale"]]
+        self.assertEqual(sake.column_justify(columns, rows, {}), ["center", "right", "left"])
+        self.assertEqual(sake.column_justify(columns, rows, {"id": "left", "#": "left"}), ["left", "left", "left"])
+
+    def test_strip_ansi(self):
+        stream = io.StringIO()
+        sake.AnsiStrippingWriter(stream).write("\x1b[1;32mok\x1b[0m")
+        self.assertEqual(stream.getvalue(), "ok")
+
+    def test_field_cache(self):
+        expe = self.experiment(params={"lr": 0.1})
+        with mock.patch.object(sake.Experiment, "_resolve_field", wraps=expe._resolve_field) as resolve:
+            self.assertEqual(expe.get_field("lr"), 0.1)
+            self.assertEqual(expe.get_field("lr"), 0.1)
+            self.assertEqual(resolve.call_count, 1)
+            expe.at_step = 1
+            expe.get_field("lr")
+            self.assertEqual(resolve.call_count, 2)
+
+    def test_varying_params(self):
+        experiments = [
+            self.experiment(params={"lr": 0.1, "seed": 1, "bs": 32}),
+            self.experiment(params={"lr": 0.2, "seed": 2, "bs": 32}),
+            self.experiment(params={"lr": 0.2, "seed": 3, "bs": 32}),
+        ]
+        self.assertEqual(sake.varying_params(experiments), ["seed", "lr"])
+
+
+if __name__ == "__main__":
+    unittest.main()
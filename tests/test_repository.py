@@ -0,0 +1,251 @@
+import json
+import os
+import time
+import unittest
+
+from helpers import SakeTestCase, checkpoint
+import sake
+
+
+class RepositoryTest(SakeTestCase):
+    def setUp(self):
+        super().setUp()
+        self.add_experiment("aaa1111", params={"lr": 0.1}, checkpoints=[checkpoint(1, {"loss": 1.0})])
+        self.add_experiment("bbb2222", params={"lr": 0.01})
+
+    def json_error(self, *args, cwd=None):
+        process = self.sake("--error-format", "json", *args, check=False, cwd=cwd)
+        self.assertEqual(process.returncode, 1)
+        return json.loads(process.stderr)
+
+    def test_bare_path_location(self):
+        (self.dir / "keepsake.yml").write_text("repository: .keepsake\n")
+        process = self.sake("list", "-q")
+        self.assertEqual(len(process.stdout.split()), 2)
+        self.assertIn("has no scheme, assuming a local path", process.stderr)
+
+    def test_unsupported_scheme(self):
+        (self.dir / "keepsake.yml").write_text("repository: s3://bucket/path\n")
+        error = self.json_error("list")
+        self.assertEqual(error["kind"], "InvalidRepository")
+        self.assertIn("unsupported repository scheme 's3://'", error["error"])
+
+    def test_missing_config(self):
+        (self.dir / "keepsake.yml").unlink()
+        error = self.json_error("list")
+        self.assertEqual(error["kind"], "InvalidRepository")
+        self.assertIn("keepsake.yml not found", error["error"])
+
+    def test_missing_experiment(self):
+        self.assertEqual(self.json_error("show", "fff"),
+                         {"error": "no experiment matching 'fff'", "kind": "ExperimentNotFound"})
+        self.assertEqual(self.json_error("rm", "f*")["kind"], "ExperimentNotFound")
+
+    def test_malformed_experiment_is_not_missing(self):
+        expe = self.add_experiment("ccc3333")
+        self.write_experiment({"id": expe, "created": "2026-01-01T10:00:00Z", "checkpoints": None, "command": "a"})
+        process = self.sake("show", "ccc", check=False)
+        self.assertNotEqual(process.returncode, 0)
+        self.assertIn("KeyError: 'params'", process.stderr)
+        self.assertNotIn("no experiment matching", process.stderr)
+
+    def test_error_kinds(self):
+        for args, kind in [
+            (["show", "*"], "AmbiguousExperiment"),
+            (["show", "aaa", "--checkpoint", "5"], "MissingCheckpoint"),
+            (["list", "--metric-delta", "loss"], "InvalidArguments"),
+            (["list", "--view", "missing"], "InvalidArguments"),
+        ]:
+            self.assertEqual(self.json_error(*args)["kind"], kind)
+
+    def test_human_errors(self):
+        for args, message in [
+            (["show", "fff"], "error: no experiment matching 'fff'\n"),
+            (["list", "-f", "lr"], "error: expected operator"),
+            (["list", "--metric-delta", "loss"], "error: --metric-delta requires --baseline\n"),
+        ]:
+            process = self.sake(*args, check=False)
+            self.assertEqual(process.returncode, 1)
+            self.assertTrue(process.stderr.startswith(message), process.stderr)
+            self.assertNotIn("Traceback", process.stderr)
+        (self.dir / "keepsake.yml").unlink()
+        process = self.sake("list", check=False)
+        self.assertEqual(process.stderr, "error: keepsake.yml not found in the current directory or its parents\n")
+
+    def test_nested_cwd(self):
+        nested = self.dir / "src" / "models"
+        nested.mkdir(parents=True)
+        process = self.sake("list", "-q", cwd=nested)
+        self.assertEqual(len(process.stdout.split()), 2)
+
+    def test_keepsake_yaml(self):
+        (self.dir / "keepsake.yml").rename(self.dir / "keepsake.yaml")
+        self.assertEqual(self.ids(), ["aaa1111", "bbb2222"])
+        (self.dir / "keepsake.yml").write_text('repository: "file://.keepsake"\n')
+        process = self.sake("list", "-q")
+        self.assertEqual(process.stderr.count("both keepsake.yml and keepsake.yaml exist"), 1)
+
+    def test_unknown_config_key(self):
+        (self.dir / "keepsake.yml").write_text('repository: "file://.keepsake"\nstorgae: file\n')
+        self.assertNotIn("unknown key", self.sake("list", "-q").stderr)
+        self.assertIn("unknown key 'storgae' in keepsake.yml", self.sake("-v", "list", "-q").stderr)
+
+    def test_yaml_config(self):
+        (self.dir / "keepsake.yml").write_text(
+            'repository: "file://.keepsake" # local\n'
+            "storage:\n  bucket: y\n  region: eu\n"
+        )
+        self.assertEqual(self.ids(), ["aaa1111", "bbb2222"])
+        config = sake.KeepsakeRepository._read_config(self.dir / "keepsake.yml")
+        self.assertEqual(config, {"repository": "file://.keepsake", "storage": {"bucket": "y", "region": "eu"}})
+        self.assertIn("bucket", self.sake("config").stdout)
+
+    def test_invalid_yaml_config(self):
+        (self.dir / "keepsake.yml").write_text("repository: [file://.keepsake\n")
+        error = self.json_error("list")
+        self.assertEqual(error["kind"], "InvalidRepository")
+        self.assertIn("invalid yaml in keepsake.yml", error["error"])
+        (self.dir / "keepsake.yml").write_text("- file://.keepsake\n")
+        self.assertIn("expected a mapping in keepsake.yml", self.json_error("list")["error"])
+
+    def test_include(self):
+        (self.dir / "base.yml").write_text('repository: "file://.keepsake"\nstorage: file\n')
+        (self.dir / "keepsake.yml").write_text("include: base.yml\nstorage: gcs\n")
+        self.assertEqual(self.ids(), ["aaa1111", "bbb2222"])
+        config = sake.KeepsakeRepository._read_config(self.dir / "keepsake.yml")
+        self.assertEqual(config, {"repository": "file://.keepsake", "storage": "gcs"})
+
+    def test_include_errors(self):
+        (self.dir / "keepsake.yml").write_text("include: keepsake.yml\n")
+        self.assertIn("cyclic include", self.json_error("list")["error"])
+        (self.dir / "keepsake.yml").write_text("include: base.yml\n")
+        self.assertIn("not found", self.json_error("list")["error"])
+        (self.dir / "base.yml").write_text("include: other.yml\n")
+        self.assertIn("cannot include another file", self.json_error("list")["error"])
+
+    def test_malformed_file_does_not_break_show(self):
+        self.write_experiment({"id": "broken"}, name="ccc3333.json")
+        self.assertIn("lr: 0.1", self.sake("show", "aaa").stdout)
+
+    def test_glob_ids(self):
+        self.assertIn("Experiment aaa1111", self.sake("show", "a*").stdout)
+        error = self.json_error("show", "*")
+        self.assertIn("Found 2 experiments matching '*'", error["error"])
+
+    def test_config_command(self):
+        stdout = self.sake("config", env={"SAKE_THREADS": "4"}).stdout
+        self.assertIn(str(self.dir / "keepsake.yml"), stdout)
+        self.assertIn("file://.keepsake", stdout)
+        self.assertIn("env", stdout)
+
+    def test_format_setting(self):
+        self.assertRegex(self.sake("config").stdout, r"format.*table.*default")
+        (self.dir / ".sake.toml").write_text('format = "jsonl"\n')
+        self.assertRegex(self.sake("config").stdout, r"format.*jsonl.*\.sake\.toml")
+        lines = self.sake("list").stdout.splitlines()
+        self.assertEqual([json.loads(line)["params"] for line in lines], [{"lr": 0.1}, {"lr": 0.01}])
+        self.assertIn("Experiments", self.sake("list", "--format", "table").stdout)
+        (self.dir / ".sake.toml").write_text('format = "xml"\n')
+        self.assertIn("invalid format 'xml' in .sake.toml", self.json_error("list")["error"])
+
+    def test_validate(self):
+        self.assertEqual(self.sake("validate").stdout.splitlines()[-1], "2 valid, 0 invalid")
+        self.write_experiment({"id": "x", "created": "yesterday", "params": [], "command": "a",
+                               "checkpoints": None}, name="bad.json")
+        process = self.sake("validate", check=False)
+        self.assertEqual(process.returncode, 1)
+        self.assertIn("invalid 'created' timestamp 'yesterday'", process.stdout)
+        self.assertIn("'params' should be dict or null, got list", process.stdout)
+        self.assertIn("2 valid, 1 invalid", process.stdout)
+
+
+class LoadTimeoutTest(SakeTestCase):
+    def test_blocked_read(self):
+        self.add_experiment("aaa1111")
+        # nothing ever writes to the fifo, reading it blocks forever
+        os.mkfifo(self.metadata / "zzz.json")
+        for args in ([], ["--parallelism", "1"], ["--parallelism", "4"]):
+            start = time.monotonic()
+            process = self.sake("list", "-q", "--timeout", "1s", *args, check=False, timeout=10)
+            self.assertNotEqual(process.returncode, 0)
+            self.assertIn("loading experiments took longer than 0:00:01", process.stderr)
+            self.assertLess(time.monotonic() - start, 5)
+
+
+class SakeTomlTest(SakeTestCase):
+    def setUp(self):
+        super().setUp()
+        self.add_experiment("aaa1111", params={"lr": 0.1})
+        (self.dir / ".sake.toml").write_text('local = false\n[views]\nsmall = ["lr"]\n')
+
+    def without_toml_parser(self):
+        # shadows the standard library, like python < 3.11 without tomli
+        stubs = self.dir / "stubs"
+        stubs.mkdir()
+        for module in ("tomllib", "tomli"):
+            (stubs / f"{module}.py").write_text(f"raise ImportError('No module named {module}')\n")
+        return {"PYTHONPATH": os.pathsep.join([str(stubs), os.environ.get("PYTHONPATH", "")])}
+
+    def test_view(self):
+        self.assertEqual(self.rows("--view", "small")[0]["params"], {"lr": 0.1})
+
+    def test_without_toml_parser(self):
+        env = self.without_toml_parser()
+        process = self.sake("list", "-q", env=env)
+        self.assertEqual(len(process.stdout.split()), 1)
+        self.assertIn("reading .sake.toml requires python >= 3.11 or the tomli package", process.stderr)
+        process = self.sake("config", env=env)
+        self.assertIn("ignoring its settings", process.stderr)
+        self.assertNotIn("Traceback", process.stderr)
+        process = self.sake("list", "--view", "small", check=False, env=env)
+        self.assertEqual(process.returncode, 1)
+        self.assertIn("requires python >= 3.11 or the tomli package", process.stderr)
+
+
+class InitTest(SakeTestCase):
+    def test_init(self):
+        (self.dir / "keepsake.yml").unlink()
+        self.sake("init", "--repo", "file://runs")
+        self.assertTrue((self.dir / "runs" / "metadata" / "experiments").is_dir())
+        self.assertEqual((self.dir / "keepsake.yml").read_text(), 'repository: "file://runs"\n')
+        process = self.sake("--error-format", "json", "init", check=False)
+        self.assertEqual(process.returncode, 1)
+        self.assertIn("already exists, use --force", process.stderr)
+        self.sake("init", "--force")
+        self.assertEqual(self.ids(), [])
+
+
+class RepositoryApiTest(SakeTestCase):
+    def setUp(self):
+        super().setUp()
+        self.add_experiment("aaa1111", checkpoints=[
+            checkpoint(2, {"loss": 0.5}), checkpoint(1, {"loss": 1.0, "note": "warmup"}), checkpoint(3, {})
+        ], extra={"kept": True})
+        self.add_experiment("bbb2222")
+        self.addCleanup(os.chdir, os.getcwd())
+        os.chdir(self.dir)
+        self.repo = sake.KeepsakeRepository()
+
+    def test_location(self):
+        os.chdir(self.metadata)
+        repo = sake.KeepsakeRepository(f"file://{self.dir / '.keepsake'}")
+        self.assertEqual(repo.location, self.dir / ".keepsake")
+        self.assertEqual(len(repo.get_experiments(parallelism=1)), 2)
+
+    def test_get_checkpoints(self):
+        steps = [(expe.id[:7], checkpoint["step"]) for expe, checkpoint in self.repo.get_checkpoints()]
+        self.assertEqual(steps, [("aaa1111", 2), ("aaa1111", 1), ("aaa1111", 3)])
+
+    def test_metric_history(self):
+        expe = self.repo.get_experiment("aaa")
+        self.assertEqual(expe.get_metric_history("loss"), [(1, 1.0), (2, 0.5)])
+        self.assertEqual(expe.get_metric_history("note"), [])
+
+    def test_to_json(self):
+        expe = self.repo.get_experiment("aaa")
+        self.assertEqual(json.loads(expe.to_json())["extra"], {"kept": True})
+        self.assertEqual(expe.to_json(indent=2), json.dumps(expe.json, indent=2))
+
+
+if __name__ == "__main__":
+    unittest.main()